@@ -0,0 +1,217 @@
+mod envelope;
+mod length;
+mod noise;
+mod square;
+mod wave;
+
+use std::collections::VecDeque;
+
+use crate::gameboy::CLOCK_SPEED;
+
+use noise::Noise;
+use square::Square;
+use wave::Wave;
+
+pub const NR50: u16 = 0xFF24;
+pub const NR51: u16 = 0xFF25;
+pub const NR52: u16 = 0xFF26;
+
+pub struct Apu {
+    square1: Square,
+    square2: Square,
+    wave: Wave,
+    noise: Noise,
+    // Master volume for each side and VIN routing
+    nr50: u8,
+    // Which channels are routed to which side
+    nr51: u8,
+    powered: bool,
+    // Step of the 512 Hz frame sequencer, from 0 to 7
+    frame_step: u8,
+    sample_rate: u32,
+    // Advances by the sample rate each T-cycle, a sample is taken each time
+    // it passes the clock speed
+    sample_clock: u32,
+    // Interleaved left and right samples waiting to be drained by the host
+    samples: VecDeque<f32>,
+}
+
+impl Apu {
+    pub fn new(sample_rate: u32) -> Apu {
+        Apu {
+            square1: Square::new(true),
+            square2: Square::new(false),
+            wave: Wave::new(),
+            noise: Noise::new(),
+            nr50: 0,
+            nr51: 0,
+            powered: false,
+            frame_step: 0,
+            // At most one sample per T-cycle keeps the sample clock below the
+            // clock speed
+            sample_rate: sample_rate.clamp(1, CLOCK_SPEED),
+            sample_clock: 0,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            0xFF10..=0xFF14 => self.square1.read(address - 0xFF10),
+            // Channel 2 has no sweep register
+            0xFF15..=0xFF19 => self.square2.read(address - 0xFF15),
+            0xFF1A..=0xFF1E => self.wave.read(address - 0xFF1A),
+            // Channel 4 has no register at NRx0
+            0xFF1F..=0xFF23 => self.noise.read(address - 0xFF1F),
+            NR50 => self.nr50,
+            NR51 => self.nr51,
+            // Channel status bits are read-only
+            NR52 => {
+                (self.powered as u8) << 7
+                    | 0x70
+                    | (self.noise.enabled() as u8) << 3
+                    | (self.wave.enabled() as u8) << 2
+                    | (self.square2.enabled() as u8) << 1
+                    | self.square1.enabled() as u8
+            }
+            0xFF30..=0xFF3F => self.wave.read_ram(address - 0xFF30),
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, data: u8) {
+        match address {
+            NR52 => self.set_power(data & 0x80 != 0),
+            // Wave RAM stays accessible with the APU off
+            0xFF30..=0xFF3F => self.wave.write_ram(address - 0xFF30, data),
+            _ if !self.powered => (),
+            0xFF10..=0xFF14 => self.square1.write(address - 0xFF10, data),
+            0xFF15..=0xFF19 => self.square2.write(address - 0xFF15, data),
+            0xFF1A..=0xFF1E => self.wave.write(address - 0xFF1A, data),
+            0xFF1F..=0xFF23 => self.noise.write(address - 0xFF1F, data),
+            NR50 => self.nr50 = data,
+            NR51 => self.nr51 = data,
+            _ => (),
+        }
+    }
+
+    // Turning the APU off clears every register but wave RAM
+    fn set_power(&mut self, powered: bool) {
+        if self.powered && !powered {
+            let ram: Vec<u8> = (0..16).map(|i| self.wave.read_ram(i)).collect();
+            self.square1 = Square::new(true);
+            self.square2 = Square::new(false);
+            self.wave = Wave::new();
+            self.noise = Noise::new();
+            for (i, data) in ram.into_iter().enumerate() {
+                self.wave.write_ram(i as u16, data);
+            }
+            self.nr50 = 0;
+            self.nr51 = 0;
+        } else if !self.powered && powered {
+            self.frame_step = 0;
+        }
+        self.powered = powered;
+    }
+
+    // Advances the channels by a number of T-cycles, taking samples at the
+    // host's sample rate along the way
+    pub fn tick(&mut self, cycles: u32) {
+        let mut cycles = cycles;
+        while cycles > 0 {
+            let until_sample = (CLOCK_SPEED - self.sample_clock).div_ceil(self.sample_rate);
+            let step = cycles.min(until_sample);
+            if self.powered {
+                self.square1.tick(step);
+                self.square2.tick(step);
+                self.wave.tick(step);
+                self.noise.tick(step);
+            }
+
+            cycles -= step;
+            self.sample_clock += step * self.sample_rate;
+            if self.sample_clock >= CLOCK_SPEED {
+                self.sample_clock -= CLOCK_SPEED;
+                self.push_sample();
+            }
+        }
+    }
+
+    // Clocked by a falling edge of DIV bit 4, every 8192 T-cycles
+    pub fn clock_frame_sequencer(&mut self) {
+        if !self.powered {
+            return;
+        }
+        // Length counters run at 256 Hz, sweep at 128 Hz and envelopes at 64 Hz
+        if self.frame_step.is_multiple_of(2) {
+            self.square1.clock_length();
+            self.square2.clock_length();
+            self.wave.clock_length();
+            self.noise.clock_length();
+        }
+        if self.frame_step == 2 || self.frame_step == 6 {
+            self.square1.clock_sweep();
+        }
+        if self.frame_step == 7 {
+            self.square1.envelope.clock();
+            self.square2.envelope.clock();
+            self.noise.envelope.clock();
+        }
+        self.frame_step = (self.frame_step + 1) % 8;
+    }
+
+    fn push_sample(&mut self) {
+        let outputs = [
+            dac(self.square1.output(), self.square1.envelope.dac_enabled()),
+            dac(self.square2.output(), self.square2.envelope.dac_enabled()),
+            dac(self.wave.output(), self.wave.dac_enabled()),
+            dac(self.noise.output(), self.noise.envelope.dac_enabled()),
+        ];
+
+        let mut left = 0.0;
+        let mut right = 0.0;
+        for (channel, output) in outputs.iter().enumerate() {
+            if self.nr51 & (0x10 << channel) != 0 {
+                left += output;
+            }
+            if self.nr51 & (0x01 << channel) != 0 {
+                right += output;
+            }
+        }
+        // Master volume goes from 1/8 to 8/8, the four channels share the range
+        let left_volume = ((self.nr50 >> 4) & 0x07) as f32 + 1.0;
+        let right_volume = (self.nr50 & 0x07) as f32 + 1.0;
+        let left = left * left_volume / 32.0;
+        let right = right * right_volume / 32.0;
+
+        // Keep at most a second of audio if the host falls behind
+        if self.samples.len() >= self.sample_rate as usize * 2 {
+            self.samples.drain(..2);
+        }
+        self.samples.push_back(left);
+        self.samples.push_back(right);
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // Moves buffered samples into the host's buffer as interleaved left and
+    // right pairs, returns the number of values written
+    pub fn drain_samples(&mut self, out: &mut [f32]) -> usize {
+        let count = out.len().min(self.samples.len()) / 2 * 2;
+        for (value, sample) in out.iter_mut().zip(self.samples.drain(..count)) {
+            *value = sample;
+        }
+        count
+    }
+}
+
+// Converts a digital output of 0-15 to an analog level from -1.0 to 1.0,
+// a disabled DAC outputs silence
+fn dac(output: u8, enabled: bool) -> f32 {
+    if !enabled {
+        return 0.0;
+    }
+    output as f32 / 7.5 - 1.0
+}
@@ -0,0 +1,60 @@
+// Volume envelope of the square and noise channels, clocked at 64 Hz
+pub struct Envelope {
+    // NRx2 as last written
+    register: u8,
+    volume: u8,
+    timer: u8,
+}
+
+impl Envelope {
+    pub fn new() -> Envelope {
+        Envelope {
+            register: 0,
+            volume: 0,
+            timer: 0,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        self.register
+    }
+
+    pub fn write(&mut self, data: u8) {
+        self.register = data;
+    }
+
+    // The DAC is off when both the initial volume and direction are zero
+    pub fn dac_enabled(&self) -> bool {
+        self.register & 0xF8 != 0
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    pub fn trigger(&mut self) {
+        self.volume = self.register >> 4;
+        self.timer = self.period();
+    }
+
+    pub fn clock(&mut self) {
+        let period = self.period();
+        if period == 0 {
+            return;
+        }
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return;
+        }
+        self.timer = period;
+        if self.register & 0x08 != 0 {
+            self.volume = (self.volume + 1).min(15);
+        } else {
+            self.volume = self.volume.saturating_sub(1);
+        }
+    }
+
+    fn period(&self) -> u8 {
+        self.register & 0x07
+    }
+}
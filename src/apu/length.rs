@@ -0,0 +1,46 @@
+// Silences a channel after a number of 256 Hz frame sequencer clocks
+pub struct LengthCounter {
+    counter: u16,
+    enabled: bool,
+    // 64 for most channels, 256 for the wave channel
+    max: u16,
+}
+
+impl LengthCounter {
+    pub fn new(max: u16) -> LengthCounter {
+        LengthCounter {
+            counter: 0,
+            enabled: false,
+            max,
+        }
+    }
+
+    // The register holds the length to subtract from the maximum
+    pub fn load(&mut self, length: u8) {
+        self.counter = self.max - length as u16;
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    // Triggering a channel with an expired length restarts it at the maximum
+    pub fn trigger(&mut self) {
+        if self.counter == 0 {
+            self.counter = self.max;
+        }
+    }
+
+    // Returns true when the counter runs out and the channel should stop
+    pub fn clock(&mut self) -> bool {
+        if !self.enabled || self.counter == 0 {
+            return false;
+        }
+        self.counter -= 1;
+        self.counter == 0
+    }
+}
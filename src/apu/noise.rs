@@ -0,0 +1,108 @@
+use super::envelope::Envelope;
+use super::length::LengthCounter;
+
+// Pseudo-random noise from a linear feedback shift register
+pub struct Noise {
+    pub length: LengthCounter,
+    pub envelope: Envelope,
+    enabled: bool,
+    // NR43 as last written, clock shift, LFSR width and divisor code
+    polynomial: u8,
+    lfsr: u16,
+    timer: u32,
+}
+
+impl Noise {
+    pub fn new() -> Noise {
+        Noise {
+            length: LengthCounter::new(64),
+            envelope: Envelope::new(),
+            enabled: false,
+            polynomial: 0,
+            lfsr: 0x7FFF,
+            timer: 0,
+        }
+    }
+
+    pub fn read(&self, register: u16) -> u8 {
+        match register {
+            0 | 1 => 0xFF,
+            2 => self.envelope.read(),
+            3 => self.polynomial,
+            _ => (self.length.enabled() as u8) << 6 | 0xBF,
+        }
+    }
+
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => (),
+            1 => self.length.load(data & 0x3F),
+            2 => {
+                self.envelope.write(data);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => self.polynomial = data,
+            _ => {
+                self.length.set_enabled(data & 0x40 != 0);
+                if data & 0x80 != 0 {
+                    self.trigger();
+                }
+            }
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.envelope.trigger();
+        self.lfsr = 0x7FFF;
+        self.timer = self.period();
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn tick(&mut self, cycles: u32) {
+        let mut cycles = cycles;
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.shift();
+        }
+        self.timer -= cycles;
+    }
+
+    // Shifts the register, feeding back the XOR of its two lowest bits
+    fn shift(&mut self) {
+        let bit = (self.lfsr ^ (self.lfsr >> 1)) & 0x01;
+        self.lfsr = (self.lfsr >> 1) | bit << 14;
+        // In 7-bit mode the result is also copied into bit 6
+        if self.polynomial & 0x08 != 0 {
+            self.lfsr = (self.lfsr & !0x40) | bit << 6;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.enabled || self.lfsr & 0x01 != 0 {
+            return 0;
+        }
+        self.envelope.volume()
+    }
+
+    fn period(&self) -> u32 {
+        let divisor = match self.polynomial & 0x07 {
+            0 => 8,
+            code => code as u32 * 16,
+        };
+        divisor << (self.polynomial >> 4)
+    }
+}
@@ -0,0 +1,200 @@
+use super::envelope::Envelope;
+use super::length::LengthCounter;
+
+// Output levels of the 8 steps of each duty cycle
+const DUTY_PATTERNS: [[u8; 8]; 4] = [
+    [0, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 1, 1, 1],
+    [0, 1, 1, 1, 1, 1, 1, 0],
+];
+
+// Frequency sweep, only present on channel 1
+struct Sweep {
+    // NR10 as last written
+    register: u8,
+    enabled: bool,
+    timer: u8,
+    shadow: u16,
+}
+
+impl Sweep {
+    fn period(&self) -> u8 {
+        (self.register >> 4) & 0x07
+    }
+
+    fn shift(&self) -> u8 {
+        self.register & 0x07
+    }
+
+    // Computes the next frequency, None if it overflows past 2047
+    fn next_frequency(&self) -> Option<u16> {
+        let delta = self.shadow >> self.shift();
+        let frequency = if self.register & 0x08 != 0 {
+            self.shadow - delta
+        } else {
+            self.shadow + delta
+        };
+        if frequency > 2047 {
+            None
+        } else {
+            Some(frequency)
+        }
+    }
+}
+
+pub struct Square {
+    sweep: Option<Sweep>,
+    pub length: LengthCounter,
+    pub envelope: Envelope,
+    enabled: bool,
+    duty: u8,
+    duty_step: u8,
+    frequency: u16,
+    // T-cycles until the next duty step
+    timer: u32,
+}
+
+impl Square {
+    pub fn new(sweep: bool) -> Square {
+        Square {
+            sweep: sweep.then_some(Sweep {
+                register: 0,
+                enabled: false,
+                timer: 8,
+                shadow: 0,
+            }),
+            length: LengthCounter::new(64),
+            envelope: Envelope::new(),
+            enabled: false,
+            duty: 0,
+            duty_step: 0,
+            frequency: 0,
+            timer: 0,
+        }
+    }
+
+    // Reads NRx0-NRx4 by register index, write-only bits read as set
+    pub fn read(&self, register: u16) -> u8 {
+        match register {
+            0 => match &self.sweep {
+                Some(sweep) => sweep.register | 0x80,
+                None => 0xFF,
+            },
+            1 => self.duty << 6 | 0x3F,
+            2 => self.envelope.read(),
+            3 => 0xFF,
+            _ => (self.length.enabled() as u8) << 6 | 0xBF,
+        }
+    }
+
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                if let Some(sweep) = &mut self.sweep {
+                    sweep.register = data & 0x7F;
+                }
+            }
+            1 => {
+                self.duty = data >> 6;
+                self.length.load(data & 0x3F);
+            }
+            2 => {
+                self.envelope.write(data);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => self.frequency = (self.frequency & 0x700) | data as u16,
+            _ => {
+                self.frequency = (self.frequency & 0xFF) | (data as u16 & 0x07) << 8;
+                self.length.set_enabled(data & 0x40 != 0);
+                if data & 0x80 != 0 {
+                    self.trigger();
+                }
+            }
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.envelope.trigger();
+        self.timer = self.period();
+
+        if let Some(sweep) = &mut self.sweep {
+            sweep.shadow = self.frequency;
+            sweep.timer = match sweep.period() {
+                0 => 8,
+                period => period,
+            };
+            sweep.enabled = sweep.period() != 0 || sweep.shift() != 0;
+            // A non-zero shift checks for overflow right away
+            if sweep.shift() != 0 && sweep.next_frequency().is_none() {
+                self.enabled = false;
+            }
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn tick(&mut self, cycles: u32) {
+        let mut cycles = cycles;
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.duty_step = (self.duty_step + 1) % 8;
+        }
+        self.timer -= cycles;
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_sweep(&mut self) {
+        let Some(sweep) = &mut self.sweep else {
+            return;
+        };
+        sweep.timer = sweep.timer.saturating_sub(1);
+        if sweep.timer > 0 {
+            return;
+        }
+        sweep.timer = match sweep.period() {
+            0 => 8,
+            period => period,
+        };
+        if !sweep.enabled || sweep.period() == 0 {
+            return;
+        }
+
+        match sweep.next_frequency() {
+            Some(frequency) if sweep.shift() != 0 => {
+                sweep.shadow = frequency;
+                self.frequency = frequency;
+                // The new frequency is checked for overflow once more
+                if sweep.next_frequency().is_none() {
+                    self.enabled = false;
+                }
+            }
+            Some(_) => (),
+            None => self.enabled = false,
+        }
+    }
+
+    // Digital output from 0 to 15
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        DUTY_PATTERNS[self.duty as usize][self.duty_step as usize] * self.envelope.volume()
+    }
+
+    fn period(&self) -> u32 {
+        (2048 - self.frequency as u32) * 4
+    }
+}
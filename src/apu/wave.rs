@@ -0,0 +1,121 @@
+use super::length::LengthCounter;
+
+// Plays back 32 4-bit samples from wave RAM
+pub struct Wave {
+    pub length: LengthCounter,
+    ram: [u8; 16],
+    enabled: bool,
+    dac_enabled: bool,
+    // NR32 output level, 0 mutes the channel
+    level: u8,
+    frequency: u16,
+    // Sample being played, from 0 to 31
+    position: u8,
+    timer: u32,
+}
+
+impl Wave {
+    pub fn new() -> Wave {
+        Wave {
+            length: LengthCounter::new(256),
+            ram: [0; 16],
+            enabled: false,
+            dac_enabled: false,
+            level: 0,
+            frequency: 0,
+            position: 0,
+            timer: 0,
+        }
+    }
+
+    pub fn read(&self, register: u16) -> u8 {
+        match register {
+            0 => (self.dac_enabled as u8) << 7 | 0x7F,
+            1 => 0xFF,
+            2 => self.level << 5 | 0x9F,
+            3 => 0xFF,
+            _ => (self.length.enabled() as u8) << 6 | 0xBF,
+        }
+    }
+
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.dac_enabled = data & 0x80 != 0;
+                if !self.dac_enabled {
+                    self.enabled = false;
+                }
+            }
+            1 => self.length.load(data),
+            2 => self.level = (data >> 5) & 0x03,
+            3 => self.frequency = (self.frequency & 0x700) | data as u16,
+            _ => {
+                self.frequency = (self.frequency & 0xFF) | (data as u16 & 0x07) << 8;
+                self.length.set_enabled(data & 0x40 != 0);
+                if data & 0x80 != 0 {
+                    self.trigger();
+                }
+            }
+        }
+    }
+
+    pub fn read_ram(&self, address: u16) -> u8 {
+        self.ram[address as usize]
+    }
+
+    pub fn write_ram(&mut self, address: u16, data: u8) {
+        self.ram[address as usize] = data;
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.dac_enabled;
+        self.length.trigger();
+        self.position = 0;
+        self.timer = self.period();
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn tick(&mut self, cycles: u32) {
+        let mut cycles = cycles;
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.position = (self.position + 1) % 32;
+        }
+        self.timer -= cycles;
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        // Each byte holds two samples, the upper nibble plays first
+        let byte = self.ram[self.position as usize / 2];
+        let sample = if self.position.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0F
+        };
+        match self.level {
+            0 => 0,
+            level => sample >> (level - 1),
+        }
+    }
+
+    fn period(&self) -> u32 {
+        (2048 - self.frequency as u32) * 2
+    }
+}
@@ -0,0 +1,170 @@
+use crate::apu::NR52;
+use crate::bus::palette::{BCPD, BCPS, OCPD, OCPS};
+use crate::bus::Bus;
+
+// Written by the CGB boot ROM to run monochrome games in compatibility mode
+pub const KEY0: u16 = 0xFF4C;
+// Writing a non-zero value unmaps the boot ROM until the next reset
+pub const BOOT: u16 = 0xFF50;
+
+// Shades of a monochrome GameBoy from white to black in RGB555
+pub const DMG_COLORS: [u16; 4] = [0x7FFF, 0x56B5, 0x294A, 0x0000];
+
+// Hardware revision whose boot ROM is emulated when none is supplied
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Model {
+    // Early DMG boot ROM
+    Dmg0,
+    #[default]
+    Dmg,
+    // GameBoy Pocket
+    Mgb,
+    // GameBoy Color, always used for games that require it
+    Cgb,
+}
+
+impl Model {
+    // The CGB boot ROM is 2 KiB longer, its header check happens on 0x100-0x1FF
+    // so the cartridge header stays visible there
+    pub fn boot_rom_size(self) -> usize {
+        match self {
+            Model::Cgb => 0x900,
+            _ => 0x100,
+        }
+    }
+}
+
+// IO registers as left behind by the boot ROM, the APU is powered on first
+// so the channel registers can be written
+const IO_STATE: [(u16, u8); 28] = [
+    (NR52, 0xF1),
+    (0xFF00, 0xCF),
+    (0xFF02, 0x7E),
+    (0xFF07, 0xF8),
+    (0xFF0F, 0xE1),
+    (0xFF10, 0x80),
+    (0xFF11, 0xBF),
+    (0xFF12, 0xF3),
+    (0xFF13, 0xFF),
+    (0xFF14, 0x3F),
+    (0xFF16, 0x3F),
+    (0xFF17, 0x00),
+    (0xFF18, 0xFF),
+    (0xFF19, 0x3F),
+    (0xFF1A, 0x7F),
+    (0xFF1B, 0xFF),
+    (0xFF1C, 0x9F),
+    (0xFF1D, 0xFF),
+    (0xFF1E, 0x3F),
+    (0xFF20, 0xFF),
+    (0xFF21, 0x00),
+    (0xFF22, 0x00),
+    (0xFF23, 0x3F),
+    (0xFF24, 0x77),
+    (0xFF25, 0xF3),
+    (0xFF40, 0x91),
+    (0xFF41, 0x85),
+    (0xFF47, 0xFC),
+];
+
+// Converts a 24-bit color into RGB555
+const fn rgb(color: u32) -> u16 {
+    let (r, g, b) = (color >> 19 & 0x1F, color >> 11 & 0x1F, color >> 3 & 0x1F);
+    (r | g << 5 | b << 10) as u16
+}
+
+// Background palette followed by the two object palettes the boot ROM
+// gives a monochrome game when no button is held. The boot ROM picks other
+// palettes for some Nintendo titles by their title checksum, that table is
+// not reproduced so every game is colored with these
+const COMPAT_PALETTES: [[u16; 4]; 3] = [
+    [rgb(0xFFFFFF), rgb(0x7BFF31), rgb(0x0063C5), rgb(0x000000)],
+    [rgb(0xFFFFFF), rgb(0xFF8484), rgb(0x943A3A), rgb(0x000000)],
+    [rgb(0xFFFFFF), rgb(0xFF8484), rgb(0x943A3A), rgb(0x000000)],
+];
+
+// Registered trademark tile drawn after the logo
+const TRADEMARK: [u8; 8] = [0x3C, 0x42, 0xB9, 0xA5, 0xB9, 0xA5, 0x42, 0x3C];
+
+// Puts the bus in the state the boot ROM would leave it in, used when the
+// boot sequence is skipped
+pub(crate) fn skip(bus: &mut Bus, model: Model) {
+    load_logo(bus);
+    for (address, data) in IO_STATE {
+        bus.write(address, data);
+    }
+    let counter = match model {
+        Model::Dmg0 => 0x1830,
+        Model::Dmg | Model::Mgb => 0xABCC,
+        Model::Cgb => 0x1EA0,
+    };
+    bus.timer_mut().set_counter(counter);
+    if model == Model::Cgb {
+        load_palettes(bus);
+    }
+}
+
+// Sum of the title bytes the CGB boot ROM computes for games published by
+// Nintendo, None for everything else
+pub(crate) fn title_checksum(bus: &Bus) -> Option<u8> {
+    let licensee = bus.read(0x014B);
+    let nintendo = licensee == 0x01
+        || (licensee == 0x33 && bus.read(0x0144) == b'0' && bus.read(0x0145) == b'1');
+    nintendo
+        .then(|| (0x0134..=0x0143).fold(0u8, |sum, address| sum.wrapping_add(bus.read(address))))
+}
+
+// Sets up the CGB palettes, games with CGB support start with white
+// backgrounds while monochrome games are colored through the compatibility
+// palettes before the CGB registers are locked
+fn load_palettes(bus: &mut Bus) {
+    let cgb_game = bus.read(0x0143) & 0x80 != 0;
+    bus.write(BCPS, 0x80);
+    for _ in 0..32 {
+        write_color(bus, BCPD, 0x7FFF);
+    }
+    if cgb_game {
+        return;
+    }
+    let [bg, obj0, obj1] = COMPAT_PALETTES;
+    bus.write(BCPS, 0x80);
+    bus.write(OCPS, 0x80);
+    for color in bg {
+        write_color(bus, BCPD, color);
+    }
+    for color in obj0.into_iter().chain(obj1) {
+        write_color(bus, OCPD, color);
+    }
+    bus.set_dmg_compat(true);
+}
+
+fn write_color(bus: &mut Bus, register: u16, color: u16) {
+    let [lo, hi] = color.to_le_bytes();
+    bus.write(register, lo);
+    bus.write(register, hi);
+}
+
+// Copies the logo from the cartridge header into VRAM, scaled up twice in
+// both directions, and lays out its tiles in the middle of the map
+fn load_logo(bus: &mut Bus) {
+    let mut address = 0x8010;
+    for i in 0..48 {
+        let data = bus.read(0x0104 + i);
+        for nibble in [data >> 4, data & 0x0F] {
+            let row = (0..4).fold(0, |row, bit| {
+                row | (((nibble >> bit) & 1) * 0b11) << (bit * 2)
+            });
+            bus.write(address, row);
+            bus.write(address + 2, row);
+            address += 4;
+        }
+    }
+    for (i, row) in TRADEMARK.into_iter().enumerate() {
+        bus.write(0x8190 + i as u16 * 2, row);
+    }
+    for i in 0..12 {
+        bus.write(0x9904 + i, 0x01 + i as u8);
+        bus.write(0x9924 + i, 0x0D + i as u8);
+    }
+    bus.write(0x9910, 0x19);
+}
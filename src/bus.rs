@@ -1,45 +1,414 @@
-pub mod rom;
-mod vram;
+pub mod cartridge;
+mod dma;
+mod hdma;
+mod hram;
+pub(crate) mod lcd;
+mod oam;
+pub(crate) mod palette;
+mod speed;
+pub(crate) mod vram;
 mod wram;
 
-use rom::Rom;
-use vram::Vram;
-use wram::Wram;
+use crate::apu::Apu;
+use crate::boot::{Model, BOOT, KEY0};
+use crate::interrupt::Interrupt;
+use crate::joypad::{Button, Joypad, P1};
+use crate::serial::{Serial, SerialLink, SB, SC};
+use crate::timer::{Timer, DIV};
+
+use cartridge::Cartridge;
+use dma::{Dma, DMA};
+use hdma::{Hdma, HDMA1, HDMA5};
+use hram::Hram;
+use lcd::Lcd;
+use oam::Oam;
+use palette::{PaletteRam, BCPD, BCPS, OCPD, OCPS};
+use speed::{Speed, KEY1};
+use vram::{Vram, VBK};
+use wram::{Wram, SVBK};
 
 pub struct Bus {
-    rom: Rom,
+    cartridge: Box<dyn Cartridge>,
     vram: Vram,
     wram: Wram,
+    oam: Oam,
+    lcd: Lcd,
+    hram: Hram,
+    timer: Timer,
+    serial: Serial,
+    apu: Apu,
+    joypad: Joypad,
+    dma: Dma,
+    hdma: Hdma,
+    // CPU T-cycles the CPU is halted for while VRAM DMA copies blocks
+    hdma_cycles: u32,
+    interrupt_enable: u8,
+    interrupt_flag: u8,
+    // Cartridge RAM was written since it was last saved
+    ram_dirty: bool,
+    // Cartridge RAM was disabled after being written, games do this once
+    // their save is complete
+    ram_disabled: bool,
+    // Overlaid on the start of the cartridge ROM until unmapped through BOOT,
+    // empty when there is none
+    boot_rom: Vec<u8>,
+    // Running on CGB hardware, and whether a monochrome game locked the CGB
+    // features away
+    cgb_hardware: bool,
+    dmg_compat: bool,
+    speed: Speed,
+    bg_palettes: PaletteRam,
+    obj_palettes: PaletteRam,
 }
 
 impl Bus {
-    pub fn new(rom: Rom) -> Bus {
+    pub fn new(cartridge: Box<dyn Cartridge>, sample_rate: u32, model: Model) -> Bus {
         Bus {
-            rom,
+            cartridge,
             vram: Vram::new(),
             wram: Wram::new(),
+            oam: Oam::new(),
+            lcd: Lcd::new(),
+            hram: Hram::new(),
+            timer: Timer::new(),
+            serial: Serial::new(),
+            apu: Apu::new(sample_rate),
+            joypad: Joypad::new(),
+            dma: Dma::new(),
+            hdma: Hdma::new(),
+            hdma_cycles: 0,
+            interrupt_enable: 0,
+            interrupt_flag: 0,
+            ram_dirty: false,
+            ram_disabled: false,
+            boot_rom: Vec::new(),
+            cgb_hardware: model == Model::Cgb,
+            dmg_compat: false,
+            speed: Speed::new(),
+            bg_palettes: PaletteRam::new(),
+            obj_palettes: PaletteRam::new(),
         }
     }
 
     pub fn read(&self, address: u16) -> u8 {
+        // During OAM DMA the CPU only sees HRAM and the IO registers
+        if self.dma.active() && address < 0xFF00 {
+            return 0xFF;
+        }
+        self.read_unchecked(address)
+    }
+
+    // Reads an address as seen from the DMA engine, ignoring bus conflicts
+    fn read_unchecked(&self, address: u16) -> u8 {
         match address {
-            0x0000..=0x3FFF => self.rom.read(address),
-            0x8000..=0x9FFF => self.vram.read(address),
-            0xC000..=0xCFFF => self.wram.read(address),
-            _ => panic!("Invalid address range."),
+            0x0000..=0x08FF if self.boot_rom_mapped(address) => self.boot_rom[address as usize],
+            // Fixed and switchable ROM banks
+            0x0000..=0x7FFF => self.cartridge.read(address),
+            0x8000..=0x9FFF => self.vram.read(address - 0x8000),
+            // External cartridge RAM
+            0xA000..=0xBFFF => self.cartridge.read_ram(address - 0xA000),
+            0xC000..=0xDFFF => self.wram.read(address - 0xC000),
+            // Echo RAM mirrors the first 7.5 KiB of WRAM
+            0xE000..=0xFDFF => self.wram.read(address - 0xE000),
+            0xFE00..=0xFE9F => self.oam.read(address - 0xFE00),
+            // Unusable region
+            0xFEA0..=0xFEFF => 0xFF,
+            P1 => self.joypad.read(),
+            SB | SC => self.serial.read(address),
+            0xFF04..=0xFF07 => self.timer.read(address),
+            // Unused upper bits of IF always read as set
+            0xFF0F => self.interrupt_flag | 0xE0,
+            DMA => self.dma.read(),
+            0xFF10..=0xFF3F => self.apu.read(address),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.lcd.read(address),
+            KEY1 if self.cgb() => self.speed.read(),
+            VBK if self.cgb() => self.vram.bank(),
+            BCPS if self.cgb() => self.bg_palettes.read_index(),
+            BCPD if self.cgb() => self.bg_palettes.read_data(),
+            OCPS if self.cgb() => self.obj_palettes.read_index(),
+            OCPD if self.cgb() => self.obj_palettes.read_data(),
+            SVBK if self.cgb() => self.wram.bank(),
+            HDMA1..=HDMA5 if self.cgb() => self.hdma.read(address),
+            KEY0 | KEY1 | VBK | BCPS..=OCPD | SVBK | BOOT | HDMA1..=HDMA5 => 0xFF,
+            // Unmapped IO addresses are open bus
+            0xFF01..=0xFF7F => 0xFF,
+            0xFF80..=0xFFFE => self.hram.read(address - 0xFF80),
+            0xFFFF => self.interrupt_enable,
         }
     }
 
     pub fn write(&mut self, address: u16, data: u8) {
+        if self.dma.active() && address < 0xFF00 {
+            return;
+        }
         match address {
-            0x0000..=0x3FFF => self.rom.write(address, data),
-            0x8000..=0x9FFF => self.vram.write(address, data),
-            0xC000..=0xCFFF => self.wram.write(address, data),
-            _ => panic!("Invalid address range."),
+            0x0000..=0x7FFF => {
+                let enabled = self.cartridge.ram_enabled();
+                self.cartridge.write(address, data);
+                if enabled && !self.cartridge.ram_enabled() && self.ram_dirty {
+                    self.ram_disabled = true;
+                }
+            }
+            0x8000..=0x9FFF => self.vram.write(address - 0x8000, data),
+            0xA000..=0xBFFF => {
+                if self.cartridge.write_ram(address - 0xA000, data) {
+                    self.ram_dirty = true;
+                }
+            }
+            0xC000..=0xDFFF => self.wram.write(address - 0xC000, data),
+            0xE000..=0xFDFF => self.wram.write(address - 0xE000, data),
+            0xFE00..=0xFE9F => self.oam.write(address - 0xFE00, data),
+            // Writes to the unusable region are ignored
+            0xFEA0..=0xFEFF => (),
+            P1 => {
+                if self.joypad.write(data) {
+                    self.request_interrupt(Interrupt::Joypad);
+                }
+            }
+            SB | SC => self.serial.write(address, data),
+            0xFF04..=0xFF07 => {
+                // Resetting DIV while the frame sequencer bit is set clocks it
+                let bit = self.frame_sequencer_bit();
+                if address == DIV && (self.timer.counter() >> bit) & 1 != 0 {
+                    self.apu.clock_frame_sequencer();
+                }
+                self.timer.write(address, data);
+            }
+            0xFF0F => self.interrupt_flag = data & 0x1F,
+            DMA => self.dma.write(data),
+            0xFF10..=0xFF3F => self.apu.write(address, data),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.lcd.write(address, data),
+            KEY0 => {
+                // Only the boot ROM gets to pick the mode
+                if self.cgb_hardware && !self.boot_rom.is_empty() {
+                    self.dmg_compat = data & 0x04 != 0;
+                }
+            }
+            KEY1 if self.cgb() => self.speed.write(data),
+            VBK if self.cgb() => self.vram.set_bank(data),
+            BCPS if self.cgb() => self.bg_palettes.write_index(data),
+            BCPD if self.cgb() => self.bg_palettes.write_data(data),
+            OCPS if self.cgb() => self.obj_palettes.write_index(data),
+            OCPD if self.cgb() => self.obj_palettes.write_data(data),
+            SVBK if self.cgb() => self.wram.set_bank(data),
+            HDMA1..=HDMA5 if self.cgb() => {
+                self.hdma.write(address, data);
+                // A general transfer runs to completion straight away, an
+                // HBlank transfer started with the LCD off copies one block
+                if address == HDMA5 && self.hdma.active() {
+                    if !self.hdma.hblank() {
+                        while self.hdma.active() {
+                            self.copy_hdma_block();
+                        }
+                    } else if self.lcd.lcdc & 0x80 == 0 {
+                        self.copy_hdma_block();
+                    }
+                }
+            }
+            // CGB registers are ignored outside of CGB mode
+            KEY1 | VBK | BCPS..=OCPD | SVBK | HDMA1..=HDMA5 => (),
+            BOOT => {
+                if data != 0 {
+                    self.boot_rom.clear();
+                }
+            }
+            0xFF01..=0xFF7F => (),
+            0xFF80..=0xFFFE => self.hram.write(address - 0xFF80, data),
+            0xFFFF => self.interrupt_enable = data,
+        }
+    }
+
+    pub(crate) fn vram(&self) -> &Vram {
+        &self.vram
+    }
+
+    pub(crate) fn vram_mut(&mut self) -> &mut Vram {
+        &mut self.vram
+    }
+
+    pub(crate) fn oam(&self) -> &Oam {
+        &self.oam
+    }
+
+    pub(crate) fn apu(&self) -> &Apu {
+        &self.apu
+    }
+
+    pub(crate) fn apu_mut(&mut self) -> &mut Apu {
+        &mut self.apu
+    }
+
+    pub(crate) fn timer_mut(&mut self) -> &mut Timer {
+        &mut self.timer
+    }
+
+    pub(crate) fn joypad(&self) -> &Joypad {
+        &self.joypad
+    }
+
+    pub(crate) fn lcd(&self) -> &Lcd {
+        &self.lcd
+    }
+
+    pub(crate) fn lcd_mut(&mut self) -> &mut Lcd {
+        &mut self.lcd
+    }
+
+    // Maps a boot ROM over the start of the cartridge, its size is checked by
+    // the caller
+    pub(crate) fn load_boot_rom(&mut self, boot_rom: Vec<u8>) {
+        self.boot_rom = boot_rom;
+    }
+
+    // The cartridge header at 0x100-0x1FF shows through the CGB boot ROM
+    fn boot_rom_mapped(&self, address: u16) -> bool {
+        (address as usize) < self.boot_rom.len() && !(0x0100..0x0200).contains(&address)
+    }
+
+    // CGB hardware running a game in CGB mode
+    pub(crate) fn cgb(&self) -> bool {
+        self.cgb_hardware && !self.dmg_compat
+    }
+
+    // CGB hardware running a monochrome game through the compatibility
+    // palettes
+    pub(crate) fn dmg_compat(&self) -> bool {
+        self.cgb_hardware && self.dmg_compat
+    }
+
+    pub(crate) fn set_dmg_compat(&mut self, dmg_compat: bool) {
+        self.dmg_compat = dmg_compat;
+    }
+
+    pub(crate) fn bg_palettes(&self) -> &PaletteRam {
+        &self.bg_palettes
+    }
+
+    pub(crate) fn obj_palettes(&self) -> &PaletteRam {
+        &self.obj_palettes
+    }
+
+    // Called by the PPU when it enters HBlank on a visible line
+    pub(crate) fn hblank(&mut self) {
+        if self.hdma.active() && self.hdma.hblank() {
+            self.copy_hdma_block();
         }
     }
 
-    pub fn get_rom(&self) -> &Rom {
-        &self.rom
+    // Copies the next block of a VRAM DMA transfer, the CPU is halted for 8
+    // M-cycles per block, or 16 in double speed (the same wall time)
+    fn copy_hdma_block(&mut self) {
+        if let Some((source, destination)) = self.hdma.next_block() {
+            for i in 0..0x10 {
+                let data = self.read_unchecked(source.wrapping_add(i));
+                self.vram.write(destination + i, data);
+            }
+            self.hdma_cycles += if self.double_speed() { 64 } else { 32 };
+        }
+    }
+
+    // CPU T-cycles spent halted by VRAM DMA since the last call
+    pub(crate) fn take_hdma_cycles(&mut self) -> u32 {
+        std::mem::take(&mut self.hdma_cycles)
+    }
+
+    pub fn double_speed(&self) -> bool {
+        self.speed.double()
+    }
+
+    // Called by STOP, returns true if a speed switch was armed and carried out
+    pub(crate) fn switch_speed(&mut self) -> bool {
+        self.cgb() && self.speed.switch()
+    }
+
+    // Bit of the internal counter that clocks the APU frame sequencer, it
+    // moves up one bit in double speed mode to keep the rate at 512 Hz
+    fn frame_sequencer_bit(&self) -> u32 {
+        if self.double_speed() {
+            13
+        } else {
+            12
+        }
+    }
+
+    // Advances the components living on the bus by a number of CPU T-cycles,
+    // the cartridge and APU run at half that rate in double speed mode
+    pub fn tick(&mut self, cycles: u32) {
+        let dots = if self.double_speed() {
+            cycles / 2
+        } else {
+            cycles
+        };
+        self.cartridge.tick(dots);
+        let counter = self.timer.counter() as u32;
+        if self.timer.tick(cycles) {
+            self.request_interrupt(Interrupt::Timer);
+        }
+        if self.serial.tick(cycles) {
+            self.request_interrupt(Interrupt::Serial);
+        }
+        // The frame sequencer steps on every falling edge of DIV bit 4, or bit
+        // 5 in double speed mode
+        self.apu.tick(dots);
+        let bit = self.frame_sequencer_bit() + 1;
+        let edges = ((counter + cycles) >> bit) - (counter >> bit);
+        for _ in 0..edges {
+            self.apu.clock_frame_sequencer();
+        }
+        for _ in 0..cycles / 4 {
+            if let Some((source, index)) = self.dma.step() {
+                let data = self.read_unchecked(source);
+                self.oam.write(index, data);
+            }
+        }
+    }
+
+    pub fn press(&mut self, button: Button) {
+        if self.joypad.press(button) {
+            self.request_interrupt(Interrupt::Joypad);
+        }
+    }
+
+    pub fn release(&mut self, button: Button) {
+        if self.joypad.release(button) {
+            self.request_interrupt(Interrupt::Joypad);
+        }
+    }
+
+    pub fn connect_serial(&mut self, link: Box<dyn SerialLink>) {
+        self.serial.connect(link);
+    }
+
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt_flag |= interrupt.mask();
+    }
+
+    pub fn clear_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt_flag &= !interrupt.mask();
+    }
+
+    // Interrupts that are both requested and enabled
+    pub fn pending_interrupts(&self) -> u8 {
+        self.interrupt_flag & self.interrupt_enable & 0x1F
+    }
+
+    pub fn ram_dirty(&self) -> bool {
+        self.ram_dirty
+    }
+
+    pub fn take_ram_disabled(&mut self) -> bool {
+        std::mem::take(&mut self.ram_disabled)
+    }
+
+    pub fn mark_ram_saved(&mut self) {
+        self.ram_dirty = false;
+    }
+
+    pub fn load_ram(&mut self, data: &[u8]) {
+        self.cartridge.load_ram(data);
+    }
+
+    pub fn get_rom(&self) -> &dyn Cartridge {
+        self.cartridge.as_ref()
     }
 }
@@ -0,0 +1,104 @@
+mod header;
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod rom_only;
+mod rtc;
+
+use std::fs;
+
+use crate::error::{Error, Result};
+
+pub use header::{CartridgeHeader, Licensee};
+
+use mbc1::Mbc1;
+use mbc2::Mbc2;
+use mbc3::Mbc3;
+use mbc5::Mbc5;
+use rom_only::RomOnly;
+
+const ROM_BANK_SIZE: usize = 0x4000;
+const RAM_BANK_SIZE: usize = 0x2000;
+
+// Memory and bank controller of a cartridge, addresses are relative to the
+// start of ROM (0x0000) and external RAM (0xA000)
+pub trait Cartridge: Send {
+    fn header(&self) -> &CartridgeHeader;
+
+    fn read(&self, address: u16) -> u8;
+
+    // ROM is read-only, writes go to the bank controller's registers
+    fn write(&mut self, address: u16, data: u8);
+
+    fn read_ram(&self, address: u16) -> u8;
+
+    // Returns true if the write was stored, writes to disabled or missing RAM
+    // are dropped
+    fn write_ram(&mut self, address: u16, data: u8) -> bool;
+
+    // Whether RAM is currently accessible, games disable it once they are
+    // done writing to it
+    fn ram_enabled(&self) -> bool;
+
+    // Advances hardware on the cartridge clocked by the system, such as the
+    // MBC3 real-time clock
+    fn tick(&mut self, _cycles: u32) {}
+
+    // Contents of the cartridge's save file
+    fn save_ram(&self) -> Vec<u8>;
+
+    fn load_ram(&mut self, data: &[u8]);
+
+    // State of the rumble motor on cartridges that have one
+    fn rumble(&self) -> bool {
+        false
+    }
+}
+
+pub fn load(path: &str) -> Result<Box<dyn Cartridge>> {
+    let buffer: Vec<u8> = fs::read(path)?;
+    from_bytes(buffer)
+}
+
+// Picks the bank controller named by the cartridge type in the header
+pub fn from_bytes(mut rom: Vec<u8>) -> Result<Box<dyn Cartridge>> {
+    let header = CartridgeHeader::parse(&rom)?;
+    // Pad the ROM to whole banks so bank reads never go out of bounds
+    let size = rom
+        .len()
+        .max(2 * ROM_BANK_SIZE)
+        .next_multiple_of(ROM_BANK_SIZE);
+    rom.resize(size, 0xFF);
+    let ram = vec![0; header.ram_bytes()];
+
+    let cartridge: Box<dyn Cartridge> = match header.cartridge_type {
+        0x00 | 0x08 | 0x09 => Box::new(RomOnly::new(header, rom, ram)),
+        0x01..=0x03 => Box::new(Mbc1::new(header, rom, ram)),
+        0x05 | 0x06 => Box::new(Mbc2::new(header, rom)),
+        0x0F..=0x13 => Box::new(Mbc3::new(header, rom, ram)),
+        0x19..=0x1E => Box::new(Mbc5::new(header, rom, ram)),
+        cartridge_type => return Err(Error::UnsupportedMapper(cartridge_type)),
+    };
+    Ok(cartridge)
+}
+
+// Reads from a 16 KiB ROM bank, banks past the end of the ROM wrap around
+fn read_rom_bank(rom: &[u8], bank: usize, address: u16) -> u8 {
+    let bank = bank % (rom.len() / ROM_BANK_SIZE);
+    rom[bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1))]
+}
+
+// Copies a save into RAM, ignoring anything past the end of it
+fn copy_ram(ram: &mut [u8], data: &[u8]) {
+    let len = ram.len().min(data.len());
+    ram[..len].copy_from_slice(&data[..len]);
+}
+
+// Offset of an address in an 8 KiB RAM bank, wrapping around smaller RAM chips
+fn ram_offset(ram: &[u8], bank: usize, address: u16) -> Option<usize> {
+    if ram.is_empty() {
+        return None;
+    }
+    Some((bank * RAM_BANK_SIZE + (address as usize & (RAM_BANK_SIZE - 1))) % ram.len())
+}
@@ -0,0 +1,232 @@
+use crate::error::{Error, Result};
+
+// Cartridge header found at 0x0100-0x014F of every ROM
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CartridgeHeader {
+    pub title: String,
+    // Four letter code only present in later cartridges, shortening the title
+    pub manufacturer: Option<String>,
+    pub cgb_flag: u8,
+    pub sgb_flag: u8,
+    pub cartridge_type: u8,
+    pub rom_size: u8,
+    pub ram_size: u8,
+    pub licensee: Licensee,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Licensee {
+    Old(u8),
+    // Two character code used when the old licensee code is 0x33
+    New(String),
+}
+
+impl CartridgeHeader {
+    pub fn parse(data: &[u8]) -> Result<CartridgeHeader> {
+        if data.len() < 0x150 {
+            return Err(Error::BadHeader(format!(
+                "ROM is {} bytes, too small to hold a header",
+                data.len()
+            )));
+        }
+
+        // The checksum covers 0x0134-0x014C and is checked by the boot ROM
+        let checksum = data[0x134..=0x14C]
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1));
+        if checksum != data[0x14D] {
+            return Err(Error::BadHeader(format!(
+                "header checksum is {:#04X} but the header sums to {:#04X}",
+                data[0x14D], checksum
+            )));
+        }
+
+        let cgb_flag = data[0x143];
+        let code = &data[0x13F..0x143];
+        let manufacturer = if cgb_flag & 0x80 != 0
+            && code
+                .iter()
+                .all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit())
+        {
+            Some(String::from_utf8_lossy(code).into_owned())
+        } else {
+            None
+        };
+        // The CGB flag and manufacturer code take over the end of the title
+        let title = match (&manufacturer, cgb_flag & 0x80 != 0) {
+            (Some(_), _) => &data[0x134..0x13F],
+            (None, true) => &data[0x134..0x143],
+            (None, false) => &data[0x134..0x144],
+        };
+        let licensee = match data[0x14B] {
+            0x33 => Licensee::New(String::from_utf8_lossy(&data[0x144..0x146]).into_owned()),
+            code => Licensee::Old(code),
+        };
+
+        Ok(CartridgeHeader {
+            title: ascii_string(title),
+            manufacturer,
+            cgb_flag,
+            sgb_flag: data[0x146],
+            cartridge_type: data[0x147],
+            rom_size: data[0x148],
+            ram_size: data[0x149],
+            licensee,
+            version: data[0x14C],
+            header_checksum: data[0x14D],
+            global_checksum: u16::from_be_bytes([data[0x14E], data[0x14F]]),
+        })
+    }
+
+    pub fn supports_cgb(&self) -> bool {
+        self.cgb_flag & 0x80 != 0
+    }
+
+    pub fn requires_cgb(&self) -> bool {
+        self.cgb_flag == 0xC0
+    }
+
+    pub fn supports_sgb(&self) -> bool {
+        self.sgb_flag == 0x03
+    }
+
+    // Cartridge RAM keeps its contents with the power off
+    pub fn has_battery(&self) -> bool {
+        matches!(
+            self.cartridge_type,
+            0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFF
+        )
+    }
+
+    // ROM size in bytes, 32 KiB shifted by the size code, codes 0x52-0x54
+    // are odd sizes listed in some documents and None is returned for codes
+    // that are not known at all
+    pub fn rom_bytes(&self) -> Option<usize> {
+        match self.rom_size {
+            0x00..=0x08 => Some(0x8000 << self.rom_size),
+            0x52 => Some(0x120000),
+            0x53 => Some(0x140000),
+            0x54 => Some(0x180000),
+            _ => None,
+        }
+    }
+
+    // External RAM size in bytes, MBC2's built-in RAM is not counted
+    pub fn ram_bytes(&self) -> usize {
+        match self.ram_size {
+            0x02 => 0x2000,
+            0x03 => 0x8000,
+            0x04 => 0x20000,
+            0x05 => 0x10000,
+            _ => 0,
+        }
+    }
+}
+
+// Titles are padded with zeroes and limited to printable ASCII
+fn ascii_string(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .take_while(|byte| **byte != 0)
+        .map(|byte| {
+            if byte.is_ascii_graphic() || *byte == b' ' {
+                *byte as char
+            } else {
+                '?'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a ROM with the given header bytes and a valid header checksum
+    fn rom(fields: &[(usize, &[u8])]) -> Vec<u8> {
+        let mut data = vec![0; 0x8000];
+        for (offset, bytes) in fields {
+            data[*offset..*offset + bytes.len()].copy_from_slice(bytes);
+        }
+        data[0x14D] = data[0x134..=0x14C]
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1));
+        data
+    }
+
+    #[test]
+    fn parses_dmg_header() {
+        let data = rom(&[
+            (0x134, b"TETRIS"),
+            (0x146, &[0x03, 0x13, 0x05, 0x03]),
+            (0x14B, &[0x01, 0x02]),
+            (0x14E, &[0x12, 0x34]),
+        ]);
+        let header = CartridgeHeader::parse(&data).unwrap();
+        assert_eq!(header.title, "TETRIS");
+        assert_eq!(header.manufacturer, None);
+        assert!(!header.supports_cgb());
+        assert!(header.supports_sgb());
+        assert!(header.has_battery());
+        assert_eq!(header.cartridge_type, 0x13);
+        assert_eq!(header.rom_bytes(), Some(0x100000));
+        assert_eq!(header.ram_bytes(), 0x8000);
+        assert_eq!(header.licensee, Licensee::Old(0x01));
+        assert_eq!(header.version, 0x02);
+        assert_eq!(header.global_checksum, 0x1234);
+    }
+
+    #[test]
+    fn parses_cgb_header_with_manufacturer() {
+        let data = rom(&[
+            (0x134, b"POKEMON_SLVAAXE"),
+            (0x143, &[0xC0]),
+            (0x144, b"01"),
+            (0x14B, &[0x33]),
+        ]);
+        let header = CartridgeHeader::parse(&data).unwrap();
+        assert_eq!(header.title, "POKEMON_SLV");
+        assert_eq!(header.manufacturer.as_deref(), Some("AAXE"));
+        assert!(header.supports_cgb());
+        assert!(header.requires_cgb());
+        assert_eq!(header.licensee, Licensee::New(String::from("01")));
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let mut data = rom(&[(0x134, b"TETRIS")]);
+        data[0x14D] ^= 0xFF;
+        assert!(matches!(
+            CartridgeHeader::parse(&data),
+            Err(Error::BadHeader(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_short_rom() {
+        assert!(matches!(
+            CartridgeHeader::parse(&[0; 0x14F]),
+            Err(Error::BadHeader(_))
+        ));
+    }
+
+    #[test]
+    fn rom_size_codes() {
+        let size = |code: u8| {
+            CartridgeHeader::parse(&rom(&[(0x148, &[code])]))
+                .unwrap()
+                .rom_bytes()
+        };
+        assert_eq!(size(0x00), Some(0x8000));
+        assert_eq!(size(0x08), Some(0x800000));
+        assert_eq!(size(0x52), Some(0x120000));
+        assert_eq!(size(0x53), Some(0x140000));
+        assert_eq!(size(0x54), Some(0x180000));
+        assert_eq!(size(0x09), None);
+        assert_eq!(size(0x50), None);
+        assert_eq!(size(0xFF), None);
+    }
+}
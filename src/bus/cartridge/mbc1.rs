@@ -0,0 +1,187 @@
+use super::{copy_ram, ram_offset, read_rom_bank, Cartridge, CartridgeHeader, ROM_BANK_SIZE};
+
+// Up to 2 MiB of ROM and 32 KiB of RAM
+pub struct Mbc1 {
+    header: CartridgeHeader,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    // Lower bits of the ROM bank, 0 is treated as 1
+    bank1: u8,
+    // Upper bits of the ROM bank or the RAM bank
+    bank2: u8,
+    // Mode 1 applies bank2 to the 0x0000-0x3FFF area and to RAM
+    mode: bool,
+    // Multicarts wire only 4 bits of bank1 to the ROM
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(header: CartridgeHeader, rom: Vec<u8>, ram: Vec<u8>) -> Mbc1 {
+        let multicart = is_multicart(&rom);
+        Mbc1 {
+            header,
+            rom,
+            ram,
+            ram_enabled: false,
+            bank1: 1,
+            bank2: 0,
+            mode: false,
+            multicart,
+        }
+    }
+
+    fn bank2_shift(&self) -> u8 {
+        if self.multicart {
+            4
+        } else {
+            5
+        }
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.mode {
+            self.bank2 as usize
+        } else {
+            0
+        }
+    }
+}
+
+// Multicarts are 1 MiB and repeat the boot logo at the start of each game,
+// every 256 KiB
+fn is_multicart(rom: &[u8]) -> bool {
+    if rom.len() != 0x100000 {
+        return false;
+    }
+    let logo = &rom[0x104..0x134];
+    (1..4)
+        .filter(|game| {
+            let start = game * 0x10 * ROM_BANK_SIZE + 0x104;
+            &rom[start..start + logo.len()] == logo
+        })
+        .count()
+        > 1
+}
+
+impl Cartridge for Mbc1 {
+    fn header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        let shift = self.bank2_shift();
+        let bank = match address {
+            0x0000..=0x3FFF if self.mode => self.bank2 << shift,
+            0x0000..=0x3FFF => 0,
+            _ if self.multicart => self.bank2 << shift | (self.bank1 & 0x0F),
+            _ => self.bank2 << shift | self.bank1,
+        };
+        read_rom_bank(&self.rom, bank as usize, address)
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = data & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.bank1 = (data & 0x1F).max(1),
+            0x4000..=0x5FFF => self.bank2 = data & 0x03,
+            _ => self.mode = data & 0x01 != 0,
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match ram_offset(&self.ram, self.ram_bank(), address) {
+            Some(offset) if self.ram_enabled => self.ram[offset],
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        match ram_offset(&self.ram, self.ram_bank(), address) {
+            Some(offset) if self.ram_enabled => {
+                self.ram[offset] = data;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn save_ram(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        copy_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ROM with its bank number written at the start of every bank
+    fn rom(size: usize, logos: &[usize]) -> Vec<u8> {
+        let mut rom = vec![0; size];
+        for bank in 0..size / ROM_BANK_SIZE {
+            rom[bank * ROM_BANK_SIZE] = bank as u8;
+        }
+        for game in logos {
+            let start = game * 0x10 * ROM_BANK_SIZE + 0x104;
+            rom[start..start + 0x30].fill(0xCE);
+        }
+        let size_code = (size / 0x8000).trailing_zeros() as u8;
+        rom[0x147..0x14A].copy_from_slice(&[0x03, size_code, 0x03]);
+        rom[0x14D] = rom[0x134..=0x14C]
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1));
+        rom
+    }
+
+    fn mbc1(rom: Vec<u8>) -> Mbc1 {
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        Mbc1::new(header, rom, vec![0; 0x8000])
+    }
+
+    #[test]
+    fn bank_zero_selects_the_next_bank() {
+        let mut mbc = mbc1(rom(0x200000, &[]));
+        for bank in [0x00, 0x20, 0x40, 0x60] {
+            mbc.write(0x4000, bank >> 5);
+            mbc.write(0x2000, bank);
+            assert_eq!(mbc.read(0x4000), bank + 1);
+        }
+    }
+
+    #[test]
+    fn mode_1_banks_low_rom_and_ram() {
+        let mut mbc = mbc1(rom(0x200000, &[]));
+        mbc.write(0x0000, 0x0A);
+        mbc.write(0x4000, 0x02);
+        assert_eq!(mbc.read(0x0000), 0x00);
+        mbc.write(0x6000, 0x01);
+        assert_eq!(mbc.read(0x0000), 0x40);
+        assert_eq!(mbc.read(0x4000), 0x41);
+
+        mbc.write_ram(0x0000, 0x55);
+        // Mode 0 always maps RAM bank 0
+        mbc.write(0x6000, 0x00);
+        assert_eq!(mbc.read_ram(0x0000), 0x00);
+        mbc.write(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(0x0000), 0x55);
+    }
+
+    #[test]
+    fn multicart_shifts_bank2_by_4() {
+        let mut mbc = mbc1(rom(0x100000, &[0, 1, 2, 3]));
+        mbc.write(0x4000, 0x01);
+        // Bit 4 of bank1 is not wired
+        mbc.write(0x2000, 0x12);
+        assert_eq!(mbc.read(0x4000), 0x12);
+        mbc.write(0x6000, 0x01);
+        assert_eq!(mbc.read(0x0000), 0x10);
+    }
+}
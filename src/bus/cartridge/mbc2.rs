@@ -0,0 +1,78 @@
+use super::{copy_ram, read_rom_bank, Cartridge, CartridgeHeader};
+
+// Up to 256 KiB of ROM and a built-in RAM of 512 half-bytes
+pub struct Mbc2 {
+    header: CartridgeHeader,
+    rom: Vec<u8>,
+    ram: [u8; 512],
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl Mbc2 {
+    pub fn new(header: CartridgeHeader, rom: Vec<u8>) -> Mbc2 {
+        Mbc2 {
+            header,
+            rom,
+            ram: [0; 512],
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+}
+
+impl Cartridge for Mbc2 {
+    fn header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank,
+        };
+        read_rom_bank(&self.rom, bank as usize, address)
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        // Bit 8 of the address picks between the two registers
+        match address {
+            0x0000..=0x3FFF if address & 0x0100 == 0 => {
+                self.ram_enabled = data & 0x0F == 0x0A;
+            }
+            0x0000..=0x3FFF => self.rom_bank = (data & 0x0F).max(1),
+            _ => (),
+        }
+    }
+
+    // Only the lower nibble is stored, the RAM repeats across 0xA000-0xBFFF
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        self.ram[address as usize & 0x01FF] | 0xF0
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        if self.ram_enabled {
+            self.ram[address as usize & 0x01FF] = data & 0x0F;
+        }
+        self.ram_enabled
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    // Saved as one byte per half-byte cell
+    fn save_ram(&self) -> Vec<u8> {
+        self.ram.to_vec()
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        copy_ram(&mut self.ram, data);
+        for cell in self.ram.iter_mut() {
+            *cell &= 0x0F;
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use super::rtc::Rtc;
+use super::{copy_ram, ram_offset, read_rom_bank, Cartridge, CartridgeHeader};
+
+// Up to 2 MiB of ROM and 32 KiB of RAM, with an optional real-time clock
+pub struct Mbc3 {
+    header: CartridgeHeader,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    rtc: Option<Rtc>,
+    // Enables both RAM and the RTC registers
+    ram_enabled: bool,
+    // 7 bit ROM bank, 0 is treated as 1
+    rom_bank: u8,
+    // RAM banks 0x00-0x03 or RTC registers 0x08-0x0C
+    ram_bank: u8,
+}
+
+impl Mbc3 {
+    pub fn new(header: CartridgeHeader, rom: Vec<u8>, ram: Vec<u8>) -> Mbc3 {
+        let rtc = match header.cartridge_type {
+            0x0F | 0x10 => Some(Rtc::new()),
+            _ => None,
+        };
+        Mbc3 {
+            header,
+            rom,
+            ram,
+            rtc,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+        }
+    }
+}
+
+impl Cartridge for Mbc3 {
+    fn header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank,
+        };
+        read_rom_bank(&self.rom, bank as usize, address)
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = data & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = (data & 0x7F).max(1),
+            0x4000..=0x5FFF => self.ram_bank = data & 0x0F,
+            _ => {
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.latch(data);
+                }
+            }
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        match (self.ram_bank, &self.rtc) {
+            (0x00..=0x03, _) => match ram_offset(&self.ram, self.ram_bank as usize, address) {
+                Some(offset) => self.ram[offset],
+                None => 0xFF,
+            },
+            (0x08..=0x0C, Some(rtc)) => rtc.read(self.ram_bank),
+            _ => 0xFF,
+        }
+    }
+
+    // Clock writes count as stored since the clock is saved along with RAM
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        if !self.ram_enabled {
+            return false;
+        }
+        match (self.ram_bank, &mut self.rtc) {
+            (0x00..=0x03, _) => match ram_offset(&self.ram, self.ram_bank as usize, address) {
+                Some(offset) => {
+                    self.ram[offset] = data;
+                    true
+                }
+                None => false,
+            },
+            (0x08..=0x0C, Some(rtc)) => {
+                rtc.write(self.ram_bank, data);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.tick(cycles);
+        }
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    // The RTC state follows the RAM contents
+    fn save_ram(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        if let Some(rtc) = &self.rtc {
+            data.extend_from_slice(&rtc.save());
+        }
+        data
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        copy_ram(&mut self.ram, data);
+        if let Some(rtc) = &mut self.rtc {
+            rtc.load(data.get(self.ram.len()..).unwrap_or_default());
+        }
+    }
+}
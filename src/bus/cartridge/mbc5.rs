@@ -0,0 +1,93 @@
+use super::{copy_ram, ram_offset, read_rom_bank, Cartridge, CartridgeHeader};
+
+// Up to 8 MiB of ROM and 128 KiB of RAM
+pub struct Mbc5 {
+    header: CartridgeHeader,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    // 9 bit ROM bank, unlike older controllers bank 0 can be selected
+    rom_bank: u16,
+    ram_bank: u8,
+    // Cartridges with a rumble motor drive it from bit 3 of the RAM bank
+    has_rumble: bool,
+    rumble: bool,
+}
+
+impl Mbc5 {
+    pub fn new(header: CartridgeHeader, rom: Vec<u8>, ram: Vec<u8>) -> Mbc5 {
+        let has_rumble = matches!(header.cartridge_type, 0x1C..=0x1E);
+        Mbc5 {
+            header,
+            rom,
+            ram,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            rumble: false,
+        }
+    }
+}
+
+impl Cartridge for Mbc5 {
+    fn header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank,
+        };
+        read_rom_bank(&self.rom, bank as usize, address)
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        match address {
+            // Only exactly 0x0A enables RAM on MBC5
+            0x0000..=0x1FFF => self.ram_enabled = data == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | data as u16,
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | (data as u16 & 0x01) << 8,
+            0x4000..=0x5FFF if self.has_rumble => {
+                self.rumble = data & 0x08 != 0;
+                self.ram_bank = data & 0x07;
+            }
+            0x4000..=0x5FFF => self.ram_bank = data & 0x0F,
+            _ => (),
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match ram_offset(&self.ram, self.ram_bank as usize, address) {
+            Some(offset) if self.ram_enabled => self.ram[offset],
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        match ram_offset(&self.ram, self.ram_bank as usize, address) {
+            Some(offset) if self.ram_enabled => {
+                self.ram[offset] = data;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn save_ram(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        copy_ram(&mut self.ram, data);
+    }
+
+    fn rumble(&self) -> bool {
+        self.rumble
+    }
+}
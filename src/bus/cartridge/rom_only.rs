@@ -0,0 +1,54 @@
+use super::{copy_ram, ram_offset, read_rom_bank, Cartridge, CartridgeHeader};
+
+// 32 KiB of ROM with up to 8 KiB of RAM and no bank controller
+pub struct RomOnly {
+    header: CartridgeHeader,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(header: CartridgeHeader, rom: Vec<u8>, ram: Vec<u8>) -> RomOnly {
+        RomOnly { header, rom, ram }
+    }
+}
+
+impl Cartridge for RomOnly {
+    fn header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        read_rom_bank(&self.rom, address as usize / 0x4000, address)
+    }
+
+    fn write(&mut self, _address: u16, _data: u8) {}
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match ram_offset(&self.ram, 0, address) {
+            Some(offset) => self.ram[offset],
+            None => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8) -> bool {
+        let Some(offset) = ram_offset(&self.ram, 0, address) else {
+            return false;
+        };
+        self.ram[offset] = data;
+        true
+    }
+
+    // Without a controller RAM is always accessible
+    fn ram_enabled(&self) -> bool {
+        true
+    }
+
+    fn save_ram(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        copy_ram(&mut self.ram, data);
+    }
+}
@@ -0,0 +1,313 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::gameboy::CLOCK_SPEED;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+// Size of the RTC state BGB and VBA append to the save file
+const RTC_SAVE_SIZE: usize = 48;
+// Older VBA versions store the timestamp in 32 bits
+const RTC_SAVE_SIZE_32: usize = 44;
+
+// Real-time clock of MBC3 cartridges, counting in emulated time
+pub struct Rtc {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    // 9 bit day counter
+    days: u16,
+    halt: bool,
+    // Set when the day counter overflows, stays set until cleared
+    day_carry: bool,
+    // Registers as seen by the CPU, copied from the clock on latch
+    latched: [u8; 5],
+    // Latching takes a write of 0x00 followed by 0x01
+    latch_armed: bool,
+    // T-cycles into the current second
+    cycles: u32,
+}
+
+impl Rtc {
+    pub fn new() -> Rtc {
+        Rtc {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days: 0,
+            halt: false,
+            day_carry: false,
+            latched: [0; 5],
+            latch_armed: false,
+            cycles: 0,
+        }
+    }
+
+    // Reads register 0x08-0x0C as selected through the RAM bank register
+    pub fn read(&self, register: u8) -> u8 {
+        let value = self.latched[(register - 0x08) as usize];
+        match register {
+            0x08 | 0x09 => value & 0x3F,
+            0x0A => value & 0x1F,
+            0x0B => value,
+            _ => value & 0xC1,
+        }
+    }
+
+    // Writes go to the running clock, not the latched copy
+    pub fn write(&mut self, register: u8, data: u8) {
+        match register {
+            0x08 => {
+                // Writing the seconds also restarts the current second
+                self.seconds = data & 0x3F;
+                self.cycles = 0;
+            }
+            0x09 => self.minutes = data & 0x3F,
+            0x0A => self.hours = data & 0x1F,
+            0x0B => self.days = (self.days & 0x100) | data as u16,
+            _ => {
+                self.days = (self.days & 0xFF) | (data as u16 & 0x01) << 8;
+                self.halt = data & 0x40 != 0;
+                self.day_carry = data & 0x80 != 0;
+            }
+        }
+    }
+
+    pub fn latch(&mut self, data: u8) {
+        if self.latch_armed && data == 0x01 {
+            self.latched = self.registers();
+        }
+        self.latch_armed = data == 0x00;
+    }
+
+    pub fn tick(&mut self, cycles: u32) {
+        if self.halt {
+            return;
+        }
+        self.cycles += cycles;
+        while self.cycles >= CLOCK_SPEED {
+            self.cycles -= CLOCK_SPEED;
+            self.advance_second();
+        }
+    }
+
+    // Values outside the normal range keep counting up to the register's
+    // width before wrapping, without carrying into the next register
+    fn advance_second(&mut self) {
+        if count(&mut self.seconds, 59, 0x3F)
+            && count(&mut self.minutes, 59, 0x3F)
+            && count(&mut self.hours, 23, 0x1F)
+        {
+            self.advance_day();
+        }
+    }
+
+    fn advance_day(&mut self) {
+        self.days += 1;
+        if self.days > 0x1FF {
+            self.days = 0;
+            self.day_carry = true;
+        }
+    }
+
+    // Catches the clock up with time that passed while the emulator was closed
+    fn advance(&mut self, mut seconds: u64) {
+        if self.halt {
+            return;
+        }
+        while seconds >= SECONDS_PER_DAY {
+            self.advance_day();
+            seconds -= SECONDS_PER_DAY;
+        }
+        for _ in 0..seconds {
+            self.advance_second();
+        }
+    }
+
+    fn registers(&self) -> [u8; 5] {
+        let flags = (self.days >> 8) as u8 | (self.halt as u8) << 6 | (self.day_carry as u8) << 7;
+        [
+            self.seconds,
+            self.minutes,
+            self.hours,
+            self.days as u8,
+            flags,
+        ]
+    }
+
+    // Clock and latched registers as 32-bit little-endian words followed by
+    // a 64-bit UNIX timestamp, the layout used by BGB and VBA
+    pub fn save(&self) -> [u8; RTC_SAVE_SIZE] {
+        let mut data = [0; RTC_SAVE_SIZE];
+        let words = self.registers().into_iter().chain(self.latched);
+        for (i, value) in words.enumerate() {
+            data[i * 4..i * 4 + 4].copy_from_slice(&(value as u32).to_le_bytes());
+        }
+        data[40..].copy_from_slice(&unix_time().to_le_bytes());
+        data
+    }
+
+    // Restores a save trailer, anything else is ignored
+    pub fn load(&mut self, data: &[u8]) {
+        let timestamp = match data.len() {
+            RTC_SAVE_SIZE => u64::from_le_bytes(data[40..48].try_into().unwrap()),
+            RTC_SAVE_SIZE_32 => u32::from_le_bytes(data[40..44].try_into().unwrap()) as u64,
+            _ => return,
+        };
+        let word = |i: usize| data[i * 4];
+        self.write(0x08, word(0));
+        self.write(0x09, word(1));
+        self.write(0x0A, word(2));
+        self.write(0x0B, word(3));
+        self.write(0x0C, word(4));
+        for (i, register) in self.latched.iter_mut().enumerate() {
+            *register = word(5 + i);
+        }
+        self.advance(unix_time().saturating_sub(timestamp));
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+// Counts a register up by one, returns true if it carries into the next one,
+// which only happens when stepping from its last value in range back to 0
+fn count(value: &mut u8, last: u8, mask: u8) -> bool {
+    if *value == last {
+        *value = 0;
+        return true;
+    }
+    *value = (*value + 1) & mask;
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Halting the clock keeps it from catching up with wall time on load
+    fn halted_rtc() -> Rtc {
+        let mut rtc = Rtc::new();
+        rtc.write(0x08, 12);
+        rtc.write(0x09, 34);
+        rtc.write(0x0A, 5);
+        rtc.write(0x0B, 0x2A);
+        rtc.write(0x0C, 0x41);
+        rtc
+    }
+
+    #[test]
+    fn save_layout() {
+        let mut rtc = halted_rtc();
+        rtc.latch(0x00);
+        rtc.latch(0x01);
+        rtc.write(0x08, 56);
+
+        let data = rtc.save();
+        let word = |i: usize| u32::from_le_bytes(data[i * 4..i * 4 + 4].try_into().unwrap());
+        assert_eq!(
+            (0..5).map(word).collect::<Vec<_>>(),
+            [56, 34, 5, 0x2A, 0x41]
+        );
+        assert_eq!(
+            (5..10).map(word).collect::<Vec<_>>(),
+            [12, 34, 5, 0x2A, 0x41]
+        );
+        let timestamp = u64::from_le_bytes(data[40..48].try_into().unwrap());
+        assert!(unix_time() - timestamp <= 1);
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let mut rtc = halted_rtc();
+        rtc.latch(0x00);
+        rtc.latch(0x01);
+        let data = rtc.save();
+
+        let mut loaded = Rtc::new();
+        loaded.load(&data);
+        assert_eq!(loaded.save()[..40], data[..40]);
+        for register in 0x08..=0x0C {
+            assert_eq!(loaded.read(register), rtc.read(register));
+        }
+    }
+
+    #[test]
+    fn loads_32_bit_timestamp() {
+        let data = halted_rtc().save();
+        let mut short = data[..RTC_SAVE_SIZE_32].to_vec();
+        short[40..44].copy_from_slice(&(unix_time() as u32).to_le_bytes());
+
+        let mut loaded = Rtc::new();
+        loaded.load(&short);
+        assert_eq!(loaded.registers(), [12, 34, 5, 0x2A, 0x41]);
+    }
+
+    #[test]
+    fn ignores_other_trailer_sizes() {
+        let mut rtc = Rtc::new();
+        rtc.load(&halted_rtc().save()[..40]);
+        assert_eq!(rtc.registers(), [0; 5]);
+    }
+
+    #[test]
+    fn latch_sequence() {
+        let mut rtc = Rtc::new();
+        rtc.write(0x08, 5);
+        assert_eq!(rtc.read(0x08), 0);
+
+        rtc.latch(0x00);
+        rtc.latch(0x01);
+        assert_eq!(rtc.read(0x08), 5);
+
+        // The latched copy holds while the clock keeps running
+        rtc.tick(CLOCK_SPEED);
+        assert_eq!(rtc.read(0x08), 5);
+
+        // Writing 0x01 alone or after anything but 0x00 does not latch
+        rtc.latch(0x01);
+        assert_eq!(rtc.read(0x08), 5);
+        rtc.latch(0x00);
+        rtc.latch(0x02);
+        rtc.latch(0x01);
+        assert_eq!(rtc.read(0x08), 5);
+
+        rtc.latch(0x00);
+        rtc.latch(0x01);
+        assert_eq!(rtc.read(0x08), 6);
+    }
+
+    #[test]
+    fn out_of_range_values_wrap_without_carry() {
+        let mut rtc = Rtc::new();
+        rtc.write(0x08, 63);
+        rtc.write(0x09, 63);
+        rtc.write(0x0A, 31);
+        rtc.tick(CLOCK_SPEED);
+        rtc.latch(0x00);
+        rtc.latch(0x01);
+        assert_eq!(rtc.read(0x08), 0);
+        assert_eq!(rtc.read(0x09), 63);
+
+        // Only 59 seconds carry into the minutes
+        rtc.write(0x08, 59);
+        rtc.write(0x09, 63);
+        rtc.tick(CLOCK_SPEED);
+        rtc.latch(0x00);
+        rtc.latch(0x01);
+        assert_eq!(rtc.read(0x09), 0);
+        assert_eq!(rtc.read(0x0A), 31);
+        assert_eq!(rtc.read(0x0B), 0);
+
+        // Hours wrapping from 31 leave the days alone
+        rtc.write(0x08, 59);
+        rtc.write(0x09, 59);
+        rtc.tick(CLOCK_SPEED);
+        rtc.latch(0x00);
+        rtc.latch(0x01);
+        assert_eq!(rtc.read(0x0A), 0);
+        assert_eq!(rtc.read(0x0B), 0);
+    }
+}
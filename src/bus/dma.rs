@@ -0,0 +1,56 @@
+pub const DMA: u16 = 0xFF46;
+
+// OAM DMA, copies 160 bytes from XX00 into OAM at one byte per M-cycle
+pub struct Dma {
+    // Last value written to 0xFF46, the upper byte of the source address
+    source: u8,
+    // Offset of the next byte to copy while a transfer is running
+    index: Option<u16>,
+    // The first byte is copied one M-cycle after the write
+    starting: bool,
+}
+
+impl Dma {
+    pub fn new() -> Dma {
+        Dma {
+            source: 0xFF,
+            index: None,
+            starting: false,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        self.source
+    }
+
+    // Starts a new transfer, restarting any transfer already running
+    pub fn write(&mut self, data: u8) {
+        self.source = data;
+        self.starting = true;
+    }
+
+    // True while the transfer holds the bus and the CPU is locked out of
+    // everything but HRAM and the IO registers
+    pub fn active(&self) -> bool {
+        self.index.is_some()
+    }
+
+    // Advances the transfer by one M-cycle, returns the source address and
+    // OAM offset of the byte to copy during it
+    pub fn step(&mut self) -> Option<(u16, u16)> {
+        if self.starting {
+            self.starting = false;
+            self.index = Some(0);
+            return None;
+        }
+
+        let index = self.index?;
+        self.index = if index < 159 { Some(index + 1) } else { None };
+        // Sources past 0xDFFF read from the WRAM echo
+        let source = match self.source {
+            0xE0..=0xFF => self.source - 0x20,
+            _ => self.source,
+        };
+        Some(((source as u16) << 8 | index, index))
+    }
+}
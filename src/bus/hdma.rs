@@ -0,0 +1,78 @@
+pub const HDMA1: u16 = 0xFF51;
+pub const HDMA2: u16 = 0xFF52;
+pub const HDMA3: u16 = 0xFF53;
+pub const HDMA4: u16 = 0xFF54;
+pub const HDMA5: u16 = 0xFF55;
+
+// CGB VRAM DMA, copies blocks of 16 bytes into VRAM either all at once or one
+// block per HBlank
+pub struct Hdma {
+    source: u16,
+    // Offset into VRAM
+    destination: u16,
+    // Blocks left to copy
+    blocks: u8,
+    active: bool,
+    hblank: bool,
+}
+
+impl Hdma {
+    pub fn new() -> Hdma {
+        Hdma {
+            source: 0,
+            destination: 0,
+            blocks: 0,
+            active: false,
+            hblank: false,
+        }
+    }
+
+    // Only HDMA5 can be read back, bit 7 is set once no transfer is running
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            HDMA5 => (!self.active as u8) << 7 | (self.blocks.wrapping_sub(1) & 0x7F),
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, data: u8) {
+        match address {
+            HDMA1 => self.source = (data as u16) << 8 | (self.source & 0x00F0),
+            HDMA2 => self.source = (self.source & 0xFF00) | (data & 0xF0) as u16,
+            HDMA3 => self.destination = ((data & 0x1F) as u16) << 8 | (self.destination & 0x00F0),
+            HDMA4 => self.destination = (self.destination & 0x1F00) | (data & 0xF0) as u16,
+            _ => {
+                // Clearing bit 7 during an HBlank transfer cancels it
+                if self.active && self.hblank && data & 0x80 == 0 {
+                    self.active = false;
+                    return;
+                }
+                self.blocks = (data & 0x7F) + 1;
+                self.hblank = data & 0x80 != 0;
+                self.active = true;
+            }
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.active
+    }
+
+    // Copies one block per HBlank rather than everything at once
+    pub fn hblank(&self) -> bool {
+        self.hblank
+    }
+
+    // Returns the source address and VRAM offset of the next block to copy
+    pub fn next_block(&mut self) -> Option<(u16, u16)> {
+        if !self.active {
+            return None;
+        }
+        let block = (self.source, self.destination);
+        self.source = self.source.wrapping_add(0x10);
+        self.destination = (self.destination + 0x10) & 0x1FF0;
+        self.blocks -= 1;
+        self.active = self.blocks > 0;
+        Some(block)
+    }
+}
@@ -0,0 +1,17 @@
+pub struct Hram {
+    data: [u8; 127],
+}
+
+impl Hram {
+    pub fn new() -> Hram {
+        Hram { data: [0; 127] }
+    }
+
+    pub fn write(&mut self, address: u16, data: u8) {
+        self.data[address as usize] = data;
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        self.data[address as usize]
+    }
+}
@@ -0,0 +1,80 @@
+pub const LCDC: u16 = 0xFF40;
+pub const STAT: u16 = 0xFF41;
+pub const SCY: u16 = 0xFF42;
+pub const SCX: u16 = 0xFF43;
+pub const LY: u16 = 0xFF44;
+pub const LYC: u16 = 0xFF45;
+pub const BGP: u16 = 0xFF47;
+pub const OBP0: u16 = 0xFF48;
+pub const OBP1: u16 = 0xFF49;
+pub const WY: u16 = 0xFF4A;
+pub const WX: u16 = 0xFF4B;
+
+// LCD registers, written by the CPU through the bus and driven by the PPU
+pub struct Lcd {
+    pub lcdc: u8,
+    pub stat: u8,
+    pub scy: u8,
+    pub scx: u8,
+    pub ly: u8,
+    pub lyc: u8,
+    pub bgp: u8,
+    pub obp0: u8,
+    pub obp1: u8,
+    pub wy: u8,
+    pub wx: u8,
+}
+
+impl Lcd {
+    pub fn new() -> Lcd {
+        Lcd {
+            lcdc: 0,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, data: u8) {
+        match address {
+            LCDC => self.lcdc = data,
+            // Mode and coincidence bits are read-only
+            STAT => self.stat = (self.stat & 0x07) | (data & 0x78),
+            SCY => self.scy = data,
+            SCX => self.scx = data,
+            // LY is read-only
+            LY => (),
+            LYC => self.lyc = data,
+            BGP => self.bgp = data,
+            OBP0 => self.obp0 = data,
+            OBP1 => self.obp1 = data,
+            WY => self.wy = data,
+            WX => self.wx = data,
+            _ => (),
+        }
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            LCDC => self.lcdc,
+            STAT => self.stat | 0x80,
+            SCY => self.scy,
+            SCX => self.scx,
+            LY => self.ly,
+            LYC => self.lyc,
+            BGP => self.bgp,
+            OBP0 => self.obp0,
+            OBP1 => self.obp1,
+            WY => self.wy,
+            WX => self.wx,
+            _ => 0xFF,
+        }
+    }
+}
@@ -0,0 +1,17 @@
+pub struct Oam {
+    data: [u8; 160],
+}
+
+impl Oam {
+    pub fn new() -> Oam {
+        Oam { data: [0; 160] }
+    }
+
+    pub fn write(&mut self, address: u16, data: u8) {
+        self.data[address as usize] = data;
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        self.data[address as usize]
+    }
+}
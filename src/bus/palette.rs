@@ -0,0 +1,49 @@
+pub const BCPS: u16 = 0xFF68;
+pub const BCPD: u16 = 0xFF69;
+pub const OCPS: u16 = 0xFF6A;
+pub const OCPD: u16 = 0xFF6B;
+
+// CGB color palette memory, 8 palettes of 4 little-endian RGB555 colors
+// accessed one byte at a time through an index and a data register
+pub struct PaletteRam {
+    data: [u8; 64],
+    index: u8,
+    // Move to the next byte after every write to the data register
+    auto_increment: bool,
+}
+
+impl PaletteRam {
+    pub fn new() -> PaletteRam {
+        PaletteRam {
+            data: [0; 64],
+            index: 0,
+            auto_increment: false,
+        }
+    }
+
+    pub fn read_index(&self) -> u8 {
+        0x40 | (self.auto_increment as u8) << 7 | self.index
+    }
+
+    pub fn write_index(&mut self, data: u8) {
+        self.index = data & 0x3F;
+        self.auto_increment = data & 0x80 != 0;
+    }
+
+    pub fn read_data(&self) -> u8 {
+        self.data[self.index as usize]
+    }
+
+    pub fn write_data(&mut self, data: u8) {
+        self.data[self.index as usize] = data;
+        if self.auto_increment {
+            self.index = (self.index + 1) & 0x3F;
+        }
+    }
+
+    // RGB555 value of a color in one of the palettes
+    pub fn color(&self, palette: u8, color: u8) -> u16 {
+        let offset = (palette as usize & 0x07) * 8 + color as usize * 2;
+        u16::from_le_bytes([self.data[offset], self.data[offset + 1]]) & 0x7FFF
+    }
+}
@@ -0,0 +1,39 @@
+pub const KEY1: u16 = 0xFF4D;
+
+// CGB CPU speed, switched by executing STOP after arming the switch in KEY1
+pub struct Speed {
+    double: bool,
+    armed: bool,
+}
+
+impl Speed {
+    pub fn new() -> Speed {
+        Speed {
+            double: false,
+            armed: false,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        0x7E | (self.double as u8) << 7 | self.armed as u8
+    }
+
+    // Only the switch armed bit is writable
+    pub fn write(&mut self, data: u8) {
+        self.armed = data & 0x01 != 0;
+    }
+
+    pub fn double(&self) -> bool {
+        self.double
+    }
+
+    // Switches speed if a switch was armed, returns true if it was
+    pub fn switch(&mut self) -> bool {
+        if !self.armed {
+            return false;
+        }
+        self.armed = false;
+        self.double = !self.double;
+        true
+    }
+}
@@ -1,17 +1,66 @@
+pub const VBK: u16 = 0xFF4F;
+
+// Tile data occupies the first 0x1800 bytes of each bank, 16 bytes per tile
+pub const TILES_PER_BANK: usize = 384;
+const TILE_DATA_END: u16 = 0x1800;
+
+// Two banks of 8 KiB, the second one only exists on CGB where it holds more
+// tiles and the tile map attributes
 pub struct Vram {
-    data: [u8; 8192],
+    data: [[u8; 8192]; 2],
+    // Bank seen by the CPU
+    bank: usize,
+    // Tiles written to since the PPU last decoded them
+    dirty_tiles: [bool; TILES_PER_BANK * 2],
+    dirty: bool,
 }
 
 impl Vram {
     pub fn new() -> Vram {
-        Vram { data: [0; 8192] }
+        Vram {
+            data: [[0; 8192]; 2],
+            bank: 0,
+            dirty_tiles: [false; TILES_PER_BANK * 2],
+            dirty: false,
+        }
     }
 
     pub fn write(&mut self, address: u16, data: u8) {
-        self.data[address as usize] = data;
+        self.data[self.bank][address as usize] = data;
+        if address < TILE_DATA_END {
+            self.dirty_tiles[self.bank * TILES_PER_BANK + address as usize / 16] = true;
+            self.dirty = true;
+        }
     }
 
     pub fn read(&self, address: u16) -> u8 {
-        self.data[address as usize]
+        self.data[self.bank][address as usize]
+    }
+
+    // Reads from a bank regardless of the one selected for the CPU
+    pub fn read_bank(&self, bank: usize, address: u16) -> u8 {
+        self.data[bank][address as usize]
+    }
+
+    pub fn bank(&self) -> u8 {
+        0xFE | self.bank as u8
+    }
+
+    pub fn set_bank(&mut self, data: u8) {
+        self.bank = (data & 0x01) as usize;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    // Returns the indices of all tiles written to since the last call, tiles
+    // in bank 1 follow those in bank 0
+    pub fn take_dirty_tiles(&mut self) -> impl Iterator<Item = usize> + '_ {
+        self.dirty = false;
+        self.dirty_tiles
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, dirty)| std::mem::take(dirty).then_some(index))
     }
 }
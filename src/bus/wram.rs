@@ -1,17 +1,42 @@
+pub const SVBK: u16 = 0xFF70;
+
+// 0xC000-0xCFFF always maps bank 0, 0xD000-0xDFFF maps bank 1 on DMG and
+// any of banks 1-7 on CGB
 pub struct Wram {
-    data: [u8; 8192],
+    data: [u8; 0x8000],
+    bank: u8,
 }
 
 impl Wram {
     pub fn new() -> Wram {
-        Wram { data: [0; 8192] }
+        Wram {
+            data: [0; 0x8000],
+            bank: 1,
+        }
     }
 
     pub fn write(&mut self, address: u16, data: u8) {
-        self.data[address as usize] = data;
+        let offset = self.offset(address);
+        self.data[offset] = data;
     }
 
     pub fn read(&self, address: u16) -> u8 {
-        self.data[address as usize]
+        self.data[self.offset(address)]
+    }
+
+    pub fn bank(&self) -> u8 {
+        0xF8 | self.bank
+    }
+
+    // Selecting bank 0 selects bank 1 instead
+    pub fn set_bank(&mut self, data: u8) {
+        self.bank = (data & 0x07).max(1);
+    }
+
+    fn offset(&self, address: u16) -> usize {
+        match address {
+            0x0000..=0x0FFF => address as usize,
+            _ => self.bank as usize * 0x1000 + (address as usize - 0x1000),
+        }
     }
 }
@@ -1,41 +1,137 @@
+use crate::boot::Model;
 use crate::bus::Bus;
+use crate::error::Error;
+use crate::interrupt::INTERRUPTS;
+use crate::timer::DIV;
 
-use self::{opcodes::AddressingMode, registers::Flag};
+use self::{
+    opcodes::AddressingMode,
+    registers::{Flag, Register, Registers},
+};
 
+mod cb_opcodes;
 mod opcodes;
 mod registers;
 
 pub struct Cpu {
-    af: u16,
-    bc: u16,
-    de: u16,
-    hl: u16,
-    sp: u16,
+    registers: Registers,
     pc: u16,
-    stack: [u8; 65536],
     halted: bool,
+    // Set by STOP until a button is pressed
+    stopped: bool,
+    // Interrupt master enable
+    ime: bool,
+    // EI only takes effect after the instruction following it
+    ime_scheduled: bool,
+    // Set when HALT fails to increment PC for the next opcode
+    halt_bug: bool,
+    // Illegal opcode and its address once the CPU has locked up
+    locked_up: Option<(u8, u16)>,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cpu {
+    // Power-on state, execution starts at the beginning of the boot ROM
     pub fn new() -> Cpu {
         Cpu {
-            af: 0,
-            bc: 0,
-            de: 0,
-            hl: 0,
-            sp: 0,
-            pc: 0x100,
-            stack: [0; 65536],
+            registers: Registers::new(),
+            pc: 0,
             halted: false,
+            stopped: false,
+            ime: false,
+            ime_scheduled: false,
+            halt_bug: false,
+            locked_up: None,
+        }
+    }
+
+    // State the boot ROM hands over to the cartridge entry point with
+    pub fn post_boot(model: Model, bus: &Bus) -> Cpu {
+        Cpu {
+            registers: Registers::post_boot(model, bus),
+            pc: 0x100,
+            ..Cpu::new()
         }
     }
 
-    pub fn cycle(&mut self, bus: &mut Bus) {
+    // Runs a single instruction and returns the number of M-cycles it took
+    pub fn cycle(&mut self, bus: &mut Bus) -> u8 {
+        // A locked up CPU no longer responds to anything, not even interrupts
+        if self.locked_up.is_some() {
+            return 1;
+        }
+        if self.stopped {
+            if !bus.joypad().input_low() {
+                return 1;
+            }
+            self.stopped = false;
+        }
+        // Any pending interrupt wakes the CPU, even with IME unset
         if self.halted {
-            return;
+            if bus.pending_interrupts() == 0 {
+                return 1;
+            }
+            self.halted = false;
+        }
+        if self.ime && bus.pending_interrupts() != 0 {
+            return self.service_interrupt(bus);
         }
+        if self.ime_scheduled {
+            self.ime_scheduled = false;
+            self.ime = true;
+        }
+
         let instruction = self.fetch_byte(bus);
-        self.execute_instruction(instruction, bus);
+        if self.halt_bug {
+            // The byte after HALT is read twice
+            self.halt_bug = false;
+            self.pc = self.pc.wrapping_sub(1);
+        }
+        self.execute_instruction(instruction, bus)
+    }
+
+    pub fn locked_up(&self) -> Option<Error> {
+        self.locked_up
+            .map(|(opcode, address)| Error::IllegalOpcode { opcode, address })
+    }
+
+    fn service_interrupt(&mut self, bus: &mut Bus) -> u8 {
+        let pending = bus.pending_interrupts();
+        let interrupt = INTERRUPTS
+            .into_iter()
+            .find(|interrupt| pending & interrupt.mask() != 0)
+            .unwrap();
+
+        self.ime = false;
+        bus.clear_interrupt(interrupt);
+        self.push_stack(bus, self.pc);
+        self.pc = interrupt.vector();
+        5
+    }
+
+    fn halt(&mut self, bus: &mut Bus) {
+        if !self.ime && bus.pending_interrupts() != 0 {
+            // HALT exits immediately but PC fails to advance past the next opcode
+            self.halt_bug = true;
+        } else {
+            self.halted = true;
+        }
+    }
+
+    fn stop(&mut self, bus: &mut Bus) {
+        // The divider is reset on entering STOP, a button already held
+        // lets the CPU carry on right away
+        bus.write(DIV, 0);
+        // On CGB an armed speed switch is carried out instead of stopping
+        if bus.switch_speed() {
+            return;
+        }
+        self.stopped = !bus.joypad().input_low();
     }
 
     fn fetch_byte(&mut self, bus: &mut Bus) -> u8 {
@@ -47,32 +143,36 @@ impl Cpu {
     fn fetch_data(&mut self, bus: &mut Bus, addressing_mode: AddressingMode) -> u16 {
         match addressing_mode {
             AddressingMode::D8 | AddressingMode::A8 | AddressingMode::R8 => {
-                return bus.read(self.pc) as u16;
+                self.fetch_byte(bus) as u16
             }
             AddressingMode::A16 | AddressingMode::D16 => {
                 let lo = self.fetch_byte(bus) as u16;
                 let hi = self.fetch_byte(bus) as u16;
-                return (hi << 8) | lo;
+                (hi << 8) | lo
             }
         }
     }
 
-    fn push_stack(&mut self, address: u16) {
+    fn push_stack(&mut self, bus: &mut Bus, address: u16) {
         let lo = (address & 0x00FF) as u8;
         let hi = (address >> 8) as u8;
-        self.sp = self.sp.wrapping_sub(1);
-        self.stack[self.sp as usize] = hi;
+        let mut sp = self.get_pair(&Register::SP);
+        sp = sp.wrapping_sub(1);
+        bus.write(sp, hi);
 
-        self.sp = self.sp.wrapping_sub(1);
-        self.stack[(self.sp + 1) as usize] = lo;
+        sp = sp.wrapping_sub(1);
+        bus.write(sp, lo);
+        self.set_pair(Register::SP, sp);
     }
 
-    fn pop_stack(&mut self) -> u16 {
-        let lo = self.stack[self.sp as usize] as u16;
-        self.sp = self.sp.wrapping_add(1);
+    fn pop_stack(&mut self, bus: &mut Bus) -> u16 {
+        let mut sp = self.get_pair(&Register::SP);
+        let lo = bus.read(sp) as u16;
+        sp = sp.wrapping_add(1);
 
-        let hi = self.stack[self.sp as usize] as u16;
-        self.sp = self.sp.wrapping_add(1);
+        let hi = bus.read(sp) as u16;
+        sp = sp.wrapping_add(1);
+        self.set_pair(Register::SP, sp);
         (hi << 8) | lo
     }
 
@@ -83,23 +183,6 @@ impl Cpu {
         };
     }
 
-    fn check_h(&mut self, a: u8, b: i8) {
-        // Check to see if lower 4 bits will result in a carry
-        if (((a & 0x0F) + (b & 0x0F) as u8) & 0x10) == 0x10 {
-            self.set_flag(Flag::H);
-        } else {
-            self.unset_flag(Flag::H);
-        }
-    }
-
-    fn check_c(&mut self, a: u8, b: u8) {
-        if (a as u16) + (b as u16) > u8::MAX as u16 {
-            self.set_flag(Flag::C);
-        } else {
-            self.unset_flag(Flag::C);
-        }
-    }
-
     fn increment_pc(&mut self) {
         self.pc = self.pc.wrapping_add(1);
     }
@@ -0,0 +1,155 @@
+use crate::bus::Bus;
+
+use super::{
+    opcodes::FetchTarget,
+    registers::{Flag, Register},
+    Cpu,
+};
+
+impl Cpu {
+    // Executes an instruction from the 0xCB prefixed page and returns the
+    // number of M-cycles it took, including the prefix byte
+    pub fn execute_cb_instruction(&mut self, instruction: u8, bus: &mut Bus) -> u8 {
+        // The lower 3 bits select the operand, the upper 5 bits the operation
+        let operand = instruction & 0x07;
+        let target = cb_operand(operand);
+        let value = self.fetch_operand(target, bus);
+        let bit = (instruction >> 3) & 0x07;
+
+        let result = match instruction >> 3 {
+            0x00 => self.rlc(value),
+            0x01 => self.rrc(value),
+            0x02 => self.rl(value),
+            0x03 => self.rr(value),
+            0x04 => self.sla(value),
+            0x05 => self.sra(value),
+            0x06 => self.swap(value),
+            0x07 => self.srl(value),
+            // BIT only tests the operand and never writes it back
+            0x08..=0x0F => {
+                self.bit(bit, value);
+                return if operand == 6 { 3 } else { 2 };
+            }
+            // RES
+            0x10..=0x17 => value & !(1 << bit),
+            // SET
+            _ => value | (1 << bit),
+        };
+        self.write_operand(target, result, bus);
+
+        if operand == 6 {
+            4
+        } else {
+            2
+        }
+    }
+
+    fn rlc(&mut self, value: u8) -> u8 {
+        let carry = value & 0x80 != 0;
+        let result = value.rotate_left(1);
+        self.set_shift_flags(result, carry);
+        result
+    }
+
+    fn rrc(&mut self, value: u8) -> u8 {
+        let carry = value & 0x01 != 0;
+        let result = value.rotate_right(1);
+        self.set_shift_flags(result, carry);
+        result
+    }
+
+    fn rl(&mut self, value: u8) -> u8 {
+        let carry = value & 0x80 != 0;
+        let result = (value << 1) | self.get_flag(Flag::C) as u8;
+        self.set_shift_flags(result, carry);
+        result
+    }
+
+    fn rr(&mut self, value: u8) -> u8 {
+        let carry = value & 0x01 != 0;
+        let result = (value >> 1) | ((self.get_flag(Flag::C) as u8) << 7);
+        self.set_shift_flags(result, carry);
+        result
+    }
+
+    fn sla(&mut self, value: u8) -> u8 {
+        let carry = value & 0x80 != 0;
+        let result = value << 1;
+        self.set_shift_flags(result, carry);
+        result
+    }
+
+    fn sra(&mut self, value: u8) -> u8 {
+        // Arithmetic shift keeps the sign bit
+        let carry = value & 0x01 != 0;
+        let result = (value >> 1) | (value & 0x80);
+        self.set_shift_flags(result, carry);
+        result
+    }
+
+    fn swap(&mut self, value: u8) -> u8 {
+        let result = value.rotate_left(4);
+        self.set_shift_flags(result, false);
+        result
+    }
+
+    fn srl(&mut self, value: u8) -> u8 {
+        let carry = value & 0x01 != 0;
+        let result = value >> 1;
+        self.set_shift_flags(result, carry);
+        result
+    }
+
+    fn bit(&mut self, bit: u8, value: u8) {
+        self.check_z((value & (1 << bit)) as u16);
+        self.unset_flag(Flag::N);
+        self.set_flag(Flag::H);
+    }
+
+    fn set_shift_flags(&mut self, result: u8, carry: bool) {
+        self.check_z(result as u16);
+        self.unset_flag(Flag::N);
+        self.unset_flag(Flag::H);
+        self.update_flag(Flag::C, carry);
+    }
+}
+
+fn cb_operand(operand: u8) -> FetchTarget {
+    match operand {
+        0 => FetchTarget::Reg(Register::B),
+        1 => FetchTarget::Reg(Register::C),
+        2 => FetchTarget::Reg(Register::D),
+        3 => FetchTarget::Reg(Register::E),
+        4 => FetchTarget::Reg(Register::H),
+        5 => FetchTarget::Reg(Register::L),
+        6 => FetchTarget::Mem(Register::HL),
+        _ => FetchTarget::Reg(Register::A),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu::opcodes::tests::af;
+
+    #[test]
+    fn bit_keeps_carry() {
+        // LD A,d8; SCF; BIT 7,A sets H and leaves C alone
+        assert_eq!(af(&[0x3E, 0x80, 0x37, 0xCB, 0x7F]), (0x80, 0x30));
+        // BIT 0,A with the bit clear sets Z
+        assert_eq!(af(&[0x3E, 0x80, 0xCB, 0x47]), (0x80, 0xA0));
+    }
+
+    #[test]
+    fn swap_clears_carry() {
+        // LD A,d8; SCF; SWAP A
+        assert_eq!(af(&[0x3E, 0xF1, 0x37, 0xCB, 0x37]), (0x1F, 0x00));
+        assert_eq!(af(&[0x3E, 0x00, 0x37, 0xCB, 0x37]), (0x00, 0x80));
+    }
+
+    #[test]
+    fn sra_keeps_sign() {
+        // LD A,d8; SRA A shifts bit 0 into the carry
+        assert_eq!(af(&[0x3E, 0x81, 0xCB, 0x2F]), (0xC0, 0x10));
+        assert_eq!(af(&[0x3E, 0x01, 0xCB, 0x2F]), (0x00, 0x90));
+    }
+}
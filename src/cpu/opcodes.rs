@@ -5,11 +5,15 @@ use super::{
     Cpu,
 };
 
+#[derive(Copy, Clone)]
 pub enum FetchTarget {
     Data(AddressingMode),
     Reg(Register),
+    // Memory pointed to by a register pair
+    Mem(Register),
 }
 
+#[derive(Copy, Clone)]
 pub enum AddressingMode {
     D8,
     D16,
@@ -18,33 +22,38 @@ pub enum AddressingMode {
     R8,
 }
 
+// M-cycles taken by each unprefixed instruction, conditional
+// instructions list the cost of the branch not being taken
+const INSTRUCTION_CYCLES: [u8; 256] = [
+    1, 3, 2, 2, 1, 1, 2, 1, 5, 2, 2, 2, 1, 1, 2, 1, 1, 3, 2, 2, 1, 1, 2, 1, 3, 2, 2, 2, 1, 1, 2, 1,
+    2, 3, 2, 2, 1, 1, 2, 1, 2, 2, 2, 2, 1, 1, 2, 1, 2, 3, 2, 2, 3, 3, 3, 1, 2, 2, 2, 2, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, 2, 2, 2, 2, 2, 2, 1, 2, 1, 1, 1, 1, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+    2, 3, 3, 4, 3, 4, 2, 4, 2, 4, 3, 1, 3, 6, 2, 4, 2, 3, 3, 1, 3, 4, 2, 4, 2, 4, 3, 1, 3, 1, 2, 4,
+    3, 3, 2, 1, 1, 4, 2, 4, 4, 1, 4, 1, 1, 1, 2, 4, 3, 3, 2, 1, 1, 4, 2, 4, 3, 2, 4, 1, 1, 1, 2, 4,
+];
+
 impl Cpu {
-    pub fn execute_instruction(&mut self, instruction: u8, bus: &mut Bus) {
-        println!(
-            "0x{:02X}:  Executing instruction: 0x{:02X}",
-            self.pc - 1,
-            instruction
-        );
+    // Executes an instruction and returns the number of M-cycles it took
+    pub fn execute_instruction(&mut self, instruction: u8, bus: &mut Bus) -> u8 {
+        let mut cycles = INSTRUCTION_CYCLES[instruction as usize];
         match instruction {
             // NOP
-            0x00 => return,
+            0x00 => (),
             // Load BC with d16
             0x01 => {
                 let value = self.fetch_data(bus, AddressingMode::D16);
-                self.bc = value;
+                self.set_pair(Register::BC, value);
             }
             // Load BC with A
-            0x02 => {
-                let a = self.get_register(&Register::A);
-                self.bc = a as u16;
-            }
+            0x02 => self.store_mem(Register::BC, Register::A, bus),
             // Increment BC
-            0x03 => {
-                self.bc = self.bc.wrapping_add(1);
-            }
+            0x03 => self.inc_pair(Register::BC),
             // INC DEC B
-            0x04 => self.inc(FetchTarget::Reg(Register::B)),
-            0x05 => self.dec(FetchTarget::Reg(Register::B)),
+            0x04 => self.inc(FetchTarget::Reg(Register::B), bus),
+            0x05 => self.dec(FetchTarget::Reg(Register::B), bus),
             // Load B with d8
             0x06 => {
                 let byte = self.fetch_data(bus, AddressingMode::D8) as u8;
@@ -60,77 +69,101 @@ impl Cpu {
                 a |= bit >> 7;
                 self.set_register(Register::A, a);
                 // Set carry flag if bit was 1
-                if bit == 0x80 {
-                    self.set_flag(Flag::C);
-                } else {
-                    self.unset_flag(Flag::C);
-                }
+                self.update_flag(Flag::C, bit == 0x80);
 
                 self.unset_flag(Flag::Z);
                 self.unset_flag(Flag::N);
                 self.unset_flag(Flag::H);
             }
-            // LD SP with a16
+            // Load a16 with SP
             0x08 => {
                 let address = self.fetch_data(bus, AddressingMode::A16);
-                self.sp = address;
+                let sp = self.get_pair(&Register::SP);
+                bus.write(address, sp as u8);
+                bus.write(address.wrapping_add(1), (sp >> 8) as u8);
             }
+            // Add BC to HL
+            0x09 => self.add_hl(self.get_pair(&Register::BC)),
             // Load A with BC
-            0x0A => {
-                self.set_register(Register::A, self.bc as u8);
-            }
+            0x0A => self.load_mem(Register::A, Register::BC, bus),
             // Decrement BC
-            0x0B => {
-                self.bc = self.bc.wrapping_sub(1);
-            }
+            0x0B => self.dec_pair(Register::BC),
             // INC DEC C
-            0x0C => self.inc(FetchTarget::Reg(Register::C)),
-            0x0D => self.dec(FetchTarget::Reg(Register::C)),
+            0x0C => self.inc(FetchTarget::Reg(Register::C), bus),
+            0x0D => self.dec(FetchTarget::Reg(Register::C), bus),
             // Load C with d8
             0x0E => {
                 let byte = self.fetch_data(bus, AddressingMode::D8) as u8;
                 self.set_register(Register::C, byte);
             }
+            // Rotate A right
+            0x0F => {
+                let mut a = self.get_register(&Register::A);
+                // Mask check if first digit will be shifted out
+                let bit = 1 & a;
+                // Shift a right
+                a >>= 1;
+                a |= bit << 7;
+                self.set_register(Register::A, a);
+                // Set carry flag if bit was 1
+                self.update_flag(Flag::C, bit == 1);
+
+                self.unset_flag(Flag::Z);
+                self.unset_flag(Flag::N);
+                self.unset_flag(Flag::H);
+            }
             // Low power standby mode
             0x10 => {
-                // TODO
+                // STOP is encoded with a trailing padding byte
+                self.fetch_byte(bus);
+                self.stop(bus);
             }
             // Load DE with d16
             0x11 => {
-                let address = self.fetch_data(bus, AddressingMode::D16);
-                self.de = address;
+                let value = self.fetch_data(bus, AddressingMode::D16);
+                self.set_pair(Register::DE, value);
             }
             // Load DE with A
-            0x12 => self.de = self.get_register(&Register::A) as u16,
-
+            0x12 => self.store_mem(Register::DE, Register::A, bus),
             // Increment DE
-            0x13 => self.de = self.de.wrapping_add(1),
+            0x13 => self.inc_pair(Register::DE),
             // INC DEC D
-            0x14 => self.inc(FetchTarget::Reg(Register::D)),
-            0x15 => self.dec(FetchTarget::Reg(Register::D)),
-            // Load H with d8
+            0x14 => self.inc(FetchTarget::Reg(Register::D), bus),
+            0x15 => self.dec(FetchTarget::Reg(Register::D), bus),
+            // Load D with d8
             0x16 => {
                 let byte = self.fetch_data(bus, AddressingMode::D8) as u8;
-                self.set_register(Register::H, byte);
+                self.set_register(Register::D, byte);
+            }
+            // Rotate A left through carry
+            0x17 => {
+                let mut a = self.get_register(&Register::A);
+                // Mask check if last digit will be shifted out
+                let bit = 0x80 & a;
+                // Shift a and carry left
+                a <<= 1;
+                a |= self.get_flag(Flag::C) as u8;
+                self.set_register(Register::A, a);
+                // Set carry flag if bit was 1
+                self.update_flag(Flag::C, bit == 0x80);
+
+                self.unset_flag(Flag::Z);
+                self.unset_flag(Flag::N);
+                self.unset_flag(Flag::H);
             }
             // Relative jump to r8
             0x18 => {
-                let address = self.fetch_data(bus, AddressingMode::R8) as i8;
-                self.pc = self.pc.wrapping_add(address as u16);
+                self.jr(bus, true);
             }
             // Add DE to HL
-            0x19 => {
-                self.unset_flag(Flag::N);
-                self.check_h(self.hl as u8, self.de as i8);
-                // TODO check carry
-            }
+            0x19 => self.add_hl(self.get_pair(&Register::DE)),
             // Load A with DE
-            0x1A => self.load(Register::A, Register::DE),
+            0x1A => self.load_mem(Register::A, Register::DE, bus),
             // Decrement DE
-            0x1B => self.de = self.de.wrapping_sub(1),
+            0x1B => self.dec_pair(Register::DE),
             // INC DEC E
-            0x1C => self.inc(FetchTarget::Reg(Register::E)),
-            0x1D => self.dec(FetchTarget::Reg(Register::E)),
+            0x1C => self.inc(FetchTarget::Reg(Register::E), bus),
+            0x1D => self.dec(FetchTarget::Reg(Register::E), bus),
             // Load E with d8
             0x1E => {
                 let data = self.fetch_data(bus, AddressingMode::D8) as u8;
@@ -139,87 +172,124 @@ impl Cpu {
             // Rotate A right through carry
             0x1F => {
                 let mut a = self.get_register(&Register::A);
-                // Mask check if last digit will be shifted out
+                // Mask check if first digit will be shifted out
                 let bit = 1 & a;
                 // Shift a and carry right
                 a >>= 1;
                 a |= (self.get_flag(Flag::C) as u8) << 7;
                 self.set_register(Register::A, a);
                 // Set carry flag if bit was 1
-                if bit == 1 {
-                    self.set_flag(Flag::C);
-                } else {
-                    self.unset_flag(Flag::C);
-                }
+                self.update_flag(Flag::C, bit == 1);
 
                 self.unset_flag(Flag::Z);
                 self.unset_flag(Flag::N);
                 self.unset_flag(Flag::H);
             }
             // Conditional relative jump if not Z
-            0x20 => {
-                if !self.get_flag(Flag::Z) {
-                    let byte = self.fetch_data(bus, AddressingMode::R8);
-                    self.pc = self.pc.wrapping_add(byte);
-                }
-            }
+            0x20 => cycles += self.jr(bus, !self.get_flag(Flag::Z)),
             // Load HL with d16
             0x21 => {
-                let bytes = self.fetch_data(bus, AddressingMode::D16);
-                self.hl = bytes;
+                let value = self.fetch_data(bus, AddressingMode::D16);
+                self.set_pair(Register::HL, value);
             }
-            // Increment HL
-            0x23 => {
-                self.hl = self.hl.wrapping_add(1);
+            // Load HL with A, increment HL
+            0x22 => {
+                self.store_mem(Register::HL, Register::A, bus);
+                self.inc_pair(Register::HL);
             }
+            // Increment HL
+            0x23 => self.inc_pair(Register::HL),
             // INC DEC H
-            0x24 => self.inc(FetchTarget::Reg(Register::H)),
-            0x25 => self.dec(FetchTarget::Reg(Register::H)),
-            // Decimal adjust A
-            0x27 => {
-                // TODO
+            0x24 => self.inc(FetchTarget::Reg(Register::H), bus),
+            0x25 => self.dec(FetchTarget::Reg(Register::H), bus),
+            // Load H with d8
+            0x26 => {
+                let byte = self.fetch_data(bus, AddressingMode::D8) as u8;
+                self.set_register(Register::H, byte);
             }
+            // Decimal adjust A
+            0x27 => self.daa(),
             // Relative jump if Z
-            0x28 => {
-                if self.get_flag(Flag::Z) {
-                    let address = self.fetch_data(bus, AddressingMode::R8) as i8;
-                    self.pc = self.pc.wrapping_add(address as u16);
-                }
-            }
+            0x28 => cycles += self.jr(bus, self.get_flag(Flag::Z)),
             // Add HL to HL
-            0x29 => {
-                self.hl = self.hl.wrapping_add(self.hl);
-
-                self.unset_flag(Flag::N);
-                self.check_h(self.hl as u8, self.hl as i8);
-                // TODO check carry
-            }
+            0x29 => self.add_hl(self.get_pair(&Register::HL)),
             // Load A with HL, increment HL
             0x2A => {
-                self.load(Register::A, Register::HL);
-                self.hl = self.hl.wrapping_add(1);
+                self.load_mem(Register::A, Register::HL, bus);
+                self.inc_pair(Register::HL);
             }
+            // Decrement HL
+            0x2B => self.dec_pair(Register::HL),
             // INC DEC L
-            0x2C => self.inc(FetchTarget::Reg(Register::L)),
-            0x2D => self.dec(FetchTarget::Reg(Register::L)),
+            0x2C => self.inc(FetchTarget::Reg(Register::L), bus),
+            0x2D => self.dec(FetchTarget::Reg(Register::L), bus),
+            // Load L with d8
+            0x2E => {
+                let byte = self.fetch_data(bus, AddressingMode::D8) as u8;
+                self.set_register(Register::L, byte);
+            }
+            // Complement A
+            0x2F => {
+                let a = self.get_register(&Register::A);
+                self.set_register(Register::A, !a);
+
+                self.set_flag(Flag::N);
+                self.set_flag(Flag::H);
+            }
+            // Relative jump if not C
+            0x30 => cycles += self.jr(bus, !self.get_flag(Flag::C)),
             // LD SP with d16
             0x31 => {
-                let address = self.fetch_data(bus, AddressingMode::D16);
-                self.sp = address;
+                let value = self.fetch_data(bus, AddressingMode::D16);
+                self.set_pair(Register::SP, value);
             }
             // Load HL with A, decrement HL
             0x32 => {
-                self.hl = self.get_register(&Register::A) as u16;
-                self.hl = self.hl.wrapping_sub(1);
+                self.store_mem(Register::HL, Register::A, bus);
+                self.dec_pair(Register::HL);
+            }
+            // Increment SP
+            0x33 => self.inc_pair(Register::SP),
+            // INC DEC HL
+            0x34 => self.inc(FetchTarget::Mem(Register::HL), bus),
+            0x35 => self.dec(FetchTarget::Mem(Register::HL), bus),
+            // Load HL with d8
+            0x36 => {
+                let byte = self.fetch_data(bus, AddressingMode::D8) as u8;
+                bus.write(self.get_pair(&Register::HL), byte);
             }
+            // Set carry flag
+            0x37 => {
+                self.unset_flag(Flag::N);
+                self.unset_flag(Flag::H);
+                self.set_flag(Flag::C);
+            }
+            // Relative jump if C
+            0x38 => cycles += self.jr(bus, self.get_flag(Flag::C)),
+            // Add SP to HL
+            0x39 => self.add_hl(self.get_pair(&Register::SP)),
+            // Load A with HL, decrement HL
+            0x3A => {
+                self.load_mem(Register::A, Register::HL, bus);
+                self.dec_pair(Register::HL);
+            }
+            // Decrement SP
+            0x3B => self.dec_pair(Register::SP),
             // INC DEC A
-            0x3C => self.inc(FetchTarget::Reg(Register::A)),
-            0x3D => self.dec(FetchTarget::Reg(Register::A)),
+            0x3C => self.inc(FetchTarget::Reg(Register::A), bus),
+            0x3D => self.dec(FetchTarget::Reg(Register::A), bus),
             // Load A with d8
             0x3E => {
                 let byte = self.fetch_data(bus, AddressingMode::D8) as u8;
                 self.set_register(Register::A, byte);
             }
+            // Complement carry flag
+            0x3F => {
+                let carry = self.get_flag(Flag::C);
+                self.unset_flag(Flag::N);
+                self.unset_flag(Flag::H);
+                self.update_flag(Flag::C, !carry);
+            }
             // B loads
             0x40 => self.load(Register::B, Register::B),
             0x41 => self.load(Register::B, Register::C),
@@ -227,7 +297,7 @@ impl Cpu {
             0x43 => self.load(Register::B, Register::E),
             0x44 => self.load(Register::B, Register::H),
             0x45 => self.load(Register::B, Register::L),
-            0x46 => self.load(Register::B, Register::HL),
+            0x46 => self.load_mem(Register::B, Register::HL, bus),
             0x47 => self.load(Register::B, Register::A),
             // C loads
             0x48 => self.load(Register::C, Register::B),
@@ -236,7 +306,7 @@ impl Cpu {
             0x4B => self.load(Register::C, Register::E),
             0x4C => self.load(Register::C, Register::H),
             0x4D => self.load(Register::C, Register::L),
-            0x4E => self.load(Register::C, Register::HL),
+            0x4E => self.load_mem(Register::C, Register::HL, bus),
             0x4F => self.load(Register::C, Register::A),
             // D loads
             0x50 => self.load(Register::D, Register::B),
@@ -245,7 +315,7 @@ impl Cpu {
             0x53 => self.load(Register::D, Register::E),
             0x54 => self.load(Register::D, Register::H),
             0x55 => self.load(Register::D, Register::L),
-            0x56 => self.load(Register::D, Register::HL),
+            0x56 => self.load_mem(Register::D, Register::HL, bus),
             0x57 => self.load(Register::D, Register::A),
             // E loads
             0x58 => self.load(Register::E, Register::B),
@@ -254,7 +324,7 @@ impl Cpu {
             0x5B => self.load(Register::E, Register::E),
             0x5C => self.load(Register::E, Register::H),
             0x5D => self.load(Register::E, Register::L),
-            0x5E => self.load(Register::E, Register::HL),
+            0x5E => self.load_mem(Register::E, Register::HL, bus),
             0x5F => self.load(Register::E, Register::A),
             // H loads
             0x60 => self.load(Register::H, Register::B),
@@ -263,7 +333,7 @@ impl Cpu {
             0x63 => self.load(Register::H, Register::E),
             0x64 => self.load(Register::H, Register::H),
             0x65 => self.load(Register::H, Register::L),
-            0x66 => self.load(Register::H, Register::HL),
+            0x66 => self.load_mem(Register::H, Register::HL, bus),
             0x67 => self.load(Register::H, Register::A),
             // L loads
             0x68 => self.load(Register::L, Register::B),
@@ -272,19 +342,19 @@ impl Cpu {
             0x6B => self.load(Register::L, Register::E),
             0x6C => self.load(Register::L, Register::H),
             0x6D => self.load(Register::L, Register::L),
-            0x6E => self.load(Register::L, Register::HL),
+            0x6E => self.load_mem(Register::L, Register::HL, bus),
             0x6F => self.load(Register::L, Register::A),
             // HL loads
-            0x70 => self.load(Register::HL, Register::B),
-            0x71 => self.load(Register::HL, Register::C),
-            0x72 => self.load(Register::HL, Register::D),
-            0x73 => self.load(Register::HL, Register::E),
-            0x74 => self.load(Register::HL, Register::H),
-            0x75 => self.load(Register::HL, Register::L),
+            0x70 => self.store_mem(Register::HL, Register::B, bus),
+            0x71 => self.store_mem(Register::HL, Register::C, bus),
+            0x72 => self.store_mem(Register::HL, Register::D, bus),
+            0x73 => self.store_mem(Register::HL, Register::E, bus),
+            0x74 => self.store_mem(Register::HL, Register::H, bus),
+            0x75 => self.store_mem(Register::HL, Register::L, bus),
             // Halt instruction 0x76 inbetween
-            0x76 => self.halted = true,
+            0x76 => self.halt(bus),
 
-            0x77 => self.load(Register::HL, Register::A),
+            0x77 => self.store_mem(Register::HL, Register::A, bus),
             // A loads
             0x78 => self.load(Register::A, Register::B),
             0x79 => self.load(Register::A, Register::C),
@@ -292,7 +362,7 @@ impl Cpu {
             0x7B => self.load(Register::A, Register::E),
             0x7C => self.load(Register::A, Register::H),
             0x7D => self.load(Register::A, Register::L),
-            0x7E => self.load(Register::A, Register::HL),
+            0x7E => self.load_mem(Register::A, Register::HL, bus),
             0x7F => self.load(Register::A, Register::A),
             // ADD operations
             0x80 => self.add(FetchTarget::Reg(Register::B), bus),
@@ -301,231 +371,349 @@ impl Cpu {
             0x83 => self.add(FetchTarget::Reg(Register::E), bus),
             0x84 => self.add(FetchTarget::Reg(Register::H), bus),
             0x85 => self.add(FetchTarget::Reg(Register::L), bus),
-            0x86 => self.add(FetchTarget::Reg(Register::HL), bus),
+            0x86 => self.add(FetchTarget::Mem(Register::HL), bus),
             0x87 => self.add(FetchTarget::Reg(Register::A), bus),
-            // Add D to A with carry
-            0x8A => {
-                let d = self.get_register(&Register::D);
-                let mut a = self.get_register(&Register::A);
-                let carry = self.get_flag(Flag::C) as u8;
-
-                a = a.wrapping_add(d + carry);
-                self.set_register(Register::A, a);
-
-                self.check_z(a as u16);
-                self.unset_flag(Flag::N);
-                self.check_h(a, d as i8);
-                // TODO check carry
-            }
+            // ADC operations
+            0x88 => self.adc(FetchTarget::Reg(Register::B), bus),
+            0x89 => self.adc(FetchTarget::Reg(Register::C), bus),
+            0x8A => self.adc(FetchTarget::Reg(Register::D), bus),
+            0x8B => self.adc(FetchTarget::Reg(Register::E), bus),
+            0x8C => self.adc(FetchTarget::Reg(Register::H), bus),
+            0x8D => self.adc(FetchTarget::Reg(Register::L), bus),
+            0x8E => self.adc(FetchTarget::Mem(Register::HL), bus),
+            0x8F => self.adc(FetchTarget::Reg(Register::A), bus),
             // SUB operations
-            0x90 => self.sub(Register::B),
-            0x91 => self.sub(Register::C),
-            0x92 => self.sub(Register::D),
-            0x93 => self.sub(Register::E),
-            0x94 => self.sub(Register::H),
-            0x95 => self.sub(Register::L),
-            0x96 => self.sub(Register::HL),
-            0x97 => self.sub(Register::A),
+            0x90 => self.sub(FetchTarget::Reg(Register::B), bus),
+            0x91 => self.sub(FetchTarget::Reg(Register::C), bus),
+            0x92 => self.sub(FetchTarget::Reg(Register::D), bus),
+            0x93 => self.sub(FetchTarget::Reg(Register::E), bus),
+            0x94 => self.sub(FetchTarget::Reg(Register::H), bus),
+            0x95 => self.sub(FetchTarget::Reg(Register::L), bus),
+            0x96 => self.sub(FetchTarget::Mem(Register::HL), bus),
+            0x97 => self.sub(FetchTarget::Reg(Register::A), bus),
+            // SBC operations
+            0x98 => self.sbc(FetchTarget::Reg(Register::B), bus),
+            0x99 => self.sbc(FetchTarget::Reg(Register::C), bus),
+            0x9A => self.sbc(FetchTarget::Reg(Register::D), bus),
+            0x9B => self.sbc(FetchTarget::Reg(Register::E), bus),
+            0x9C => self.sbc(FetchTarget::Reg(Register::H), bus),
+            0x9D => self.sbc(FetchTarget::Reg(Register::L), bus),
+            0x9E => self.sbc(FetchTarget::Mem(Register::HL), bus),
+            0x9F => self.sbc(FetchTarget::Reg(Register::A), bus),
             // AND operations
-            0xA0 => self.and(Register::B),
-            0xA1 => self.and(Register::C),
-            0xA2 => self.and(Register::D),
-            0xA3 => self.and(Register::E),
-            0xA4 => self.and(Register::H),
-            0xA5 => self.and(Register::L),
-            0xA6 => self.and(Register::HL),
-            0xA7 => self.and(Register::A),
+            0xA0 => self.and(FetchTarget::Reg(Register::B), bus),
+            0xA1 => self.and(FetchTarget::Reg(Register::C), bus),
+            0xA2 => self.and(FetchTarget::Reg(Register::D), bus),
+            0xA3 => self.and(FetchTarget::Reg(Register::E), bus),
+            0xA4 => self.and(FetchTarget::Reg(Register::H), bus),
+            0xA5 => self.and(FetchTarget::Reg(Register::L), bus),
+            0xA6 => self.and(FetchTarget::Mem(Register::HL), bus),
+            0xA7 => self.and(FetchTarget::Reg(Register::A), bus),
             // XOR operations
-            0xA8 => self.xor(Register::B),
-            0xA9 => self.xor(Register::C),
-            0xAA => self.xor(Register::D),
-            0xAB => self.xor(Register::E),
-            0xAC => self.xor(Register::H),
-            0xAD => self.xor(Register::L),
-            0xAE => self.xor(Register::HL),
-            0xAF => self.xor(Register::A),
+            0xA8 => self.xor(FetchTarget::Reg(Register::B), bus),
+            0xA9 => self.xor(FetchTarget::Reg(Register::C), bus),
+            0xAA => self.xor(FetchTarget::Reg(Register::D), bus),
+            0xAB => self.xor(FetchTarget::Reg(Register::E), bus),
+            0xAC => self.xor(FetchTarget::Reg(Register::H), bus),
+            0xAD => self.xor(FetchTarget::Reg(Register::L), bus),
+            0xAE => self.xor(FetchTarget::Mem(Register::HL), bus),
+            0xAF => self.xor(FetchTarget::Reg(Register::A), bus),
             // OR operations
-            0xB0 => self.or(Register::B),
-            0xB1 => self.or(Register::C),
-            0xB2 => self.or(Register::D),
-            0xB3 => self.or(Register::E),
-            0xB4 => self.or(Register::H),
-            0xB5 => self.or(Register::L),
-            0xB6 => self.or(Register::HL),
-            0xB7 => self.or(Register::A),
+            0xB0 => self.or(FetchTarget::Reg(Register::B), bus),
+            0xB1 => self.or(FetchTarget::Reg(Register::C), bus),
+            0xB2 => self.or(FetchTarget::Reg(Register::D), bus),
+            0xB3 => self.or(FetchTarget::Reg(Register::E), bus),
+            0xB4 => self.or(FetchTarget::Reg(Register::H), bus),
+            0xB5 => self.or(FetchTarget::Reg(Register::L), bus),
+            0xB6 => self.or(FetchTarget::Mem(Register::HL), bus),
+            0xB7 => self.or(FetchTarget::Reg(Register::A), bus),
             // CP operations
-            0xB8 => self.cp(Register::B),
-            0xB9 => self.cp(Register::C),
-            0xBA => self.cp(Register::D),
-            0xBB => self.cp(Register::E),
-            0xBC => self.cp(Register::H),
-            0xBD => self.cp(Register::L),
-            0xBE => self.cp(Register::HL),
-            0xBF => self.cp(Register::A),
+            0xB8 => self.cp(FetchTarget::Reg(Register::B), bus),
+            0xB9 => self.cp(FetchTarget::Reg(Register::C), bus),
+            0xBA => self.cp(FetchTarget::Reg(Register::D), bus),
+            0xBB => self.cp(FetchTarget::Reg(Register::E), bus),
+            0xBC => self.cp(FetchTarget::Reg(Register::H), bus),
+            0xBD => self.cp(FetchTarget::Reg(Register::L), bus),
+            0xBE => self.cp(FetchTarget::Mem(Register::HL), bus),
+            0xBF => self.cp(FetchTarget::Reg(Register::A), bus),
             // Return if not Z
-            0xC0 => {
-                if !self.get_flag(Flag::Z) {
-                    self.ret();
-                }
-            }
-            // Jump to nn
+            0xC0 => cycles += self.ret(bus, !self.get_flag(Flag::Z)),
+            // Pop BC
+            0xC1 => {
+                let value = self.pop_stack(bus);
+                self.set_pair(Register::BC, value);
+            }
+            // Jump to a16 if not Z
+            0xC2 => cycles += self.jp(bus, !self.get_flag(Flag::Z)),
+            // Jump to a16
             0xC3 => {
-                let address = self.fetch_data(bus, AddressingMode::A16);
-                self.pc = address;
-            }
+                self.jp(bus, true);
+            }
+            // Call to a16 if not Z
+            0xC4 => cycles += self.call(bus, !self.get_flag(Flag::Z)),
+            // Push BC
+            0xC5 => self.push_stack(bus, self.get_pair(&Register::BC)),
+            // Add d8 to A
+            0xC6 => self.add(FetchTarget::Data(AddressingMode::D8), bus),
             // RST 00H
-            0xC7 => {
-                // TODO
-            }
+            0xC7 => self.rst(bus, 0x00),
             // Return if Z
-            0xC8 => {
-                if self.get_flag(Flag::Z) {
-                    self.ret();
-                }
-            }
+            0xC8 => cycles += self.ret(bus, self.get_flag(Flag::Z)),
             // Return
-            0xC9 => self.ret(),
+            0xC9 => {
+                self.ret(bus, true);
+            }
+            // Jump to a16 if Z
+            0xCA => cycles += self.jp(bus, self.get_flag(Flag::Z)),
             // Prefix CB
             0xCB => {
-                // TODO lookup table
+                let instruction = self.fetch_byte(bus);
+                cycles = self.execute_cb_instruction(instruction, bus);
             }
+            // Call to a16 if Z
+            0xCC => cycles += self.call(bus, self.get_flag(Flag::Z)),
             // Call to a16
-            0xCD => self.call(bus),
-            // Add d8 to A with carry
-            0xCE => {
-                let data = self.fetch_data(bus, AddressingMode::D8) as u8;
-                let mut a = self.get_register(&Register::A);
-                let carry = self.get_flag(Flag::C) as u8;
-
-                a = a.wrapping_add(data + carry);
-                self.set_register(Register::A, a);
-
-                self.check_z(a as u16);
-                self.unset_flag(Flag::N);
-                self.check_h(a, data as i8);
-                // TODO check carry
+            0xCD => {
+                self.call(bus, true);
             }
-            // Jump if C
-            0xD2 => {
-                if self.get_flag(Flag::C) {
-                    let address = self.fetch_data(bus, AddressingMode::A16);
-                    self.pc = address;
-                }
+            // Add d8 to A with carry
+            0xCE => self.adc(FetchTarget::Data(AddressingMode::D8), bus),
+            // RST 08H
+            0xCF => self.rst(bus, 0x08),
+            // Return if not C
+            0xD0 => cycles += self.ret(bus, !self.get_flag(Flag::C)),
+            // Pop DE
+            0xD1 => {
+                let value = self.pop_stack(bus);
+                self.set_pair(Register::DE, value);
+            }
+            // Jump to a16 if not C
+            0xD2 => cycles += self.jp(bus, !self.get_flag(Flag::C)),
+            // Call to a16 if not C
+            0xD4 => cycles += self.call(bus, !self.get_flag(Flag::C)),
+            // Push DE
+            0xD5 => self.push_stack(bus, self.get_pair(&Register::DE)),
+            // Subtract d8 from A
+            0xD6 => self.sub(FetchTarget::Data(AddressingMode::D8), bus),
+            // RST 10H
+            0xD7 => self.rst(bus, 0x10),
+            // Return if C
+            0xD8 => cycles += self.ret(bus, self.get_flag(Flag::C)),
+            // Return and enable interrupts
+            0xD9 => {
+                self.ret(bus, true);
+                self.ime = true;
+            }
+            // Jump to a16 if C
+            0xDA => cycles += self.jp(bus, self.get_flag(Flag::C)),
+            // Call to a16 if C
+            0xDC => cycles += self.call(bus, self.get_flag(Flag::C)),
+            // Subtract d8 from A with carry
+            0xDE => self.sbc(FetchTarget::Data(AddressingMode::D8), bus),
+            // RST 18H
+            0xDF => self.rst(bus, 0x18),
+            // Write A to IO port a8
+            0xE0 => {
+                let offset = self.fetch_data(bus, AddressingMode::A8);
+                bus.write(0xFF00 | offset, self.get_register(&Register::A));
             }
-            // Call to 18
-            0xDF => {
-                // TODO
+            // Pop HL
+            0xE1 => {
+                let value = self.pop_stack(bus);
+                self.set_pair(Register::HL, value);
             }
-            // Write to IO port n
-            0xE0 => {
-                // TODO
+            // Write A to IO port C
+            0xE2 => {
+                let offset = self.get_register(&Register::C) as u16;
+                bus.write(0xFF00 | offset, self.get_register(&Register::A));
             }
             // Push HL
-            0xE5 => {
-                // TODO
+            0xE5 => self.push_stack(bus, self.get_pair(&Register::HL)),
+            // AND d8 with A
+            0xE6 => self.and(FetchTarget::Data(AddressingMode::D8), bus),
+            // RST 20H
+            0xE7 => self.rst(bus, 0x20),
+            // Add r8 to SP
+            0xE8 => {
+                let value = self.add_sp(bus);
+                self.set_pair(Register::SP, value);
             }
             // Jump to HL
-            0xE9 => self.pc = self.hl,
+            0xE9 => self.pc = self.get_pair(&Register::HL),
             // Load a16 with A
             0xEA => {
-                // TODO
+                let address = self.fetch_data(bus, AddressingMode::A16);
+                bus.write(address, self.get_register(&Register::A));
             }
-            // Read from IO port n
+            // XOR d8 with A
+            0xEE => self.xor(FetchTarget::Data(AddressingMode::D8), bus),
+            // RST 28H
+            0xEF => self.rst(bus, 0x28),
+            // Read A from IO port a8
             0xF0 => {
-                // TODO
-            }
+                let offset = self.fetch_data(bus, AddressingMode::A8);
+                self.set_register(Register::A, bus.read(0xFF00 | offset));
+            }
+            // Pop AF
+            0xF1 => {
+                let value = self.pop_stack(bus);
+                self.set_pair(Register::AF, value);
+            }
+            // Read A from IO port C
+            0xF2 => {
+                let offset = self.get_register(&Register::C) as u16;
+                self.set_register(Register::A, bus.read(0xFF00 | offset));
+            }
+            // Disable interrupts
+            0xF3 => {
+                self.ime = false;
+                self.ime_scheduled = false;
+            }
+            // Push AF
+            0xF5 => self.push_stack(bus, self.get_pair(&Register::AF)),
+            // OR d8 with A
+            0xF6 => self.or(FetchTarget::Data(AddressingMode::D8), bus),
             // RST 30H
-            0xF7 => {
-                // TODO
-            }
-            // Call to 38
-            0xFF => {
-                // TODO
-            }
-            // BLANKS temporary catch
+            0xF7 => self.rst(bus, 0x30),
+            // Load HL with SP + r8
+            0xF8 => {
+                let value = self.add_sp(bus);
+                self.set_pair(Register::HL, value);
+            }
+            // Load SP with HL
+            0xF9 => self.set_pair(Register::SP, self.get_pair(&Register::HL)),
+            // Load A with a16
+            0xFA => {
+                let address = self.fetch_data(bus, AddressingMode::A16);
+                self.set_register(Register::A, bus.read(address));
+            }
+            // Enable interrupts
+            0xFB => self.ime_scheduled = true,
+            // Compare d8 with A
+            0xFE => self.cp(FetchTarget::Data(AddressingMode::D8), bus),
+            // RST 38H
+            0xFF => self.rst(bus, 0x38),
+            // Illegal opcodes hang the CPU until it is reset
             0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD => {
-                println!("Invalid read");
+                self.locked_up = Some((instruction, self.pc.wrapping_sub(1)));
             }
-            _ => panic!(
-                "Invalid instruction read: 0x{:02X} at 0x{:02X}",
-                instruction,
-                self.pc - 1
-            ),
         }
+        cycles
     }
 
-    fn inc(&mut self, target: FetchTarget) {
+    pub fn fetch_operand(&mut self, target: FetchTarget, bus: &mut Bus) -> u8 {
         match target {
-            FetchTarget::Data(_) => panic!("tried to inc address."),
-            FetchTarget::Reg(r) => {
-                let mut value = self.get_register(&r);
-                self.check_h(value, 1);
-                value = value.wrapping_add(1);
-                self.set_register(r, value);
-                self.check_z(value as u16);
-                self.unset_flag(Flag::N);
-            }
+            FetchTarget::Data(d) => self.fetch_data(bus, d) as u8,
+            FetchTarget::Reg(r) => self.get_register(&r),
+            FetchTarget::Mem(r) => bus.read(self.get_pair(&r)),
         }
     }
 
-    fn dec(&mut self, target: FetchTarget) {
+    pub fn write_operand(&mut self, target: FetchTarget, value: u8, bus: &mut Bus) {
         match target {
-            FetchTarget::Data(_) => panic!("tried to dec address."),
-            FetchTarget::Reg(r) => {
-                let mut value = self.get_register(&r);
-                self.check_h(value, -1);
-                value = value.wrapping_sub(1);
-                self.set_register(r, value);
-                self.check_z(value as u16);
-                self.set_flag(Flag::N);
-            }
+            FetchTarget::Data(_) => panic!("tried to write to immediate data."),
+            FetchTarget::Reg(r) => self.set_register(r, value),
+            FetchTarget::Mem(r) => bus.write(self.get_pair(&r), value),
         }
     }
 
+    fn inc(&mut self, target: FetchTarget, bus: &mut Bus) {
+        let mut value = self.fetch_operand(target, bus);
+        self.update_flag(Flag::H, value & 0x0F == 0x0F);
+        value = value.wrapping_add(1);
+        self.write_operand(target, value, bus);
+        self.check_z(value as u16);
+        self.unset_flag(Flag::N);
+    }
+
+    fn dec(&mut self, target: FetchTarget, bus: &mut Bus) {
+        let mut value = self.fetch_operand(target, bus);
+        self.update_flag(Flag::H, value & 0x0F == 0);
+        value = value.wrapping_sub(1);
+        self.write_operand(target, value, bus);
+        self.check_z(value as u16);
+        self.set_flag(Flag::N);
+    }
+
+    fn inc_pair(&mut self, register: Register) {
+        let value = self.get_pair(&register).wrapping_add(1);
+        self.set_pair(register, value);
+    }
+
+    fn dec_pair(&mut self, register: Register) {
+        let value = self.get_pair(&register).wrapping_sub(1);
+        self.set_pair(register, value);
+    }
+
     fn load(&mut self, reg1: Register, reg2: Register) {
         let r = self.get_register(&reg2);
         self.set_register(reg1, r)
     }
 
+    fn load_mem(&mut self, reg: Register, pair: Register, bus: &mut Bus) {
+        let value = bus.read(self.get_pair(&pair));
+        self.set_register(reg, value)
+    }
+
+    fn store_mem(&mut self, pair: Register, reg: Register, bus: &mut Bus) {
+        let value = self.get_register(&reg);
+        bus.write(self.get_pair(&pair), value)
+    }
+
     fn add(&mut self, target: FetchTarget, bus: &mut Bus) {
-        let a = self.get_register(&Register::A);
-        match target {
-            FetchTarget::Data(d) => {
-                let d = self.fetch_data(bus, d);
-                let value = a.wrapping_add(d as u8);
-                self.set_register(Register::A, value);
-                self.check_z(value as u16);
-                self.unset_flag(Flag::N);
-                self.check_h(a, d as i8);
-                self.check_c(a, d as u8);
-            }
-            FetchTarget::Reg(r) => {
-                let r = self.get_register(&r);
-                let value = a.wrapping_add(r);
-                self.set_register(Register::A, value);
-                self.check_z(value as u16);
-                self.unset_flag(Flag::N);
-                self.check_h(a, r as i8);
-                self.check_c(a, r);
-            }
-        }
+        self.add_with_carry(target, bus, false);
+    }
+
+    fn adc(&mut self, target: FetchTarget, bus: &mut Bus) {
+        let carry = self.get_flag(Flag::C);
+        self.add_with_carry(target, bus, carry);
     }
 
-    fn sub(&mut self, reg: Register) {
+    fn add_with_carry(&mut self, target: FetchTarget, bus: &mut Bus, carry: bool) {
         let a = self.get_register(&Register::A);
-        let r = self.get_register(&reg);
-        let value = a.wrapping_sub(r);
+        let r = self.fetch_operand(target, bus);
+        let carry = carry as u8;
+        let value = a.wrapping_add(r).wrapping_add(carry);
+        self.set_register(Register::A, value);
+
+        self.check_z(value as u16);
+        self.unset_flag(Flag::N);
+        self.update_flag(Flag::H, (a & 0x0F) + (r & 0x0F) + carry > 0x0F);
+        self.update_flag(Flag::C, a as u16 + r as u16 + carry as u16 > 0xFF);
+    }
+
+    fn sub(&mut self, target: FetchTarget, bus: &mut Bus) {
+        let value = self.subtract_with_carry(target, bus, false);
+        self.set_register(Register::A, value);
+    }
+
+    fn sbc(&mut self, target: FetchTarget, bus: &mut Bus) {
+        let carry = self.get_flag(Flag::C);
+        let value = self.subtract_with_carry(target, bus, carry);
         self.set_register(Register::A, value);
+    }
+
+    fn cp(&mut self, target: FetchTarget, bus: &mut Bus) {
+        // Compare is a subtraction that only keeps the flags
+        self.subtract_with_carry(target, bus, false);
+    }
+
+    fn subtract_with_carry(&mut self, target: FetchTarget, bus: &mut Bus, carry: bool) -> u8 {
+        let a = self.get_register(&Register::A);
+        let r = self.fetch_operand(target, bus);
+        let carry = carry as u8;
+        let value = a.wrapping_sub(r).wrapping_sub(carry);
 
         self.check_z(value as u16);
         self.set_flag(Flag::N);
-        self.check_h(a, r as i8);
-        // TODO check carry
+        self.update_flag(Flag::H, (a & 0x0F) < (r & 0x0F) + carry);
+        self.update_flag(Flag::C, (a as u16) < r as u16 + carry as u16);
+        value
     }
 
-    fn and(&mut self, reg: Register) {
+    fn and(&mut self, target: FetchTarget, bus: &mut Bus) {
         let a = self.get_register(&Register::A);
-        let r = self.get_register(&reg);
+        let r = self.fetch_operand(target, bus);
         let value = a & r;
         self.set_register(Register::A, value);
 
@@ -535,9 +723,9 @@ impl Cpu {
         self.unset_flag(Flag::C);
     }
 
-    fn xor(&mut self, reg: Register) {
+    fn xor(&mut self, target: FetchTarget, bus: &mut Bus) {
         let a = self.get_register(&Register::A);
-        let r = self.get_register(&reg);
+        let r = self.fetch_operand(target, bus);
         let value = a ^ r;
         self.set_register(Register::A, value);
 
@@ -547,9 +735,9 @@ impl Cpu {
         self.unset_flag(Flag::C);
     }
 
-    fn or(&mut self, reg: Register) {
+    fn or(&mut self, target: FetchTarget, bus: &mut Bus) {
         let a = self.get_register(&Register::A);
-        let r = self.get_register(&reg);
+        let r = self.fetch_operand(target, bus);
         let value = a | r;
         self.set_register(Register::A, value);
 
@@ -559,23 +747,182 @@ impl Cpu {
         self.unset_flag(Flag::C);
     }
 
-    fn cp(&mut self, reg: Register) {
-        let a = self.get_register(&Register::A);
-        let r = self.get_register(&reg);
-        self.check_h(a, -(r as i8));
-        self.check_z(0);
-        self.set_flag(Flag::N);
-        // TODO Check carry
+    fn add_hl(&mut self, value: u16) {
+        let hl = self.get_pair(&Register::HL);
+        self.set_pair(Register::HL, hl.wrapping_add(value));
+
+        self.unset_flag(Flag::N);
+        self.update_flag(Flag::H, (hl & 0x0FFF) + (value & 0x0FFF) > 0x0FFF);
+        self.update_flag(Flag::C, hl as u32 + value as u32 > 0xFFFF);
+    }
+
+    fn add_sp(&mut self, bus: &mut Bus) -> u16 {
+        let offset = self.fetch_data(bus, AddressingMode::R8) as u8;
+        // Flags are computed from the unsigned low byte addition
+        let sp = self.get_pair(&Register::SP);
+        let value = sp.wrapping_add(offset as i8 as u16);
+
+        self.unset_flag(Flag::Z);
+        self.unset_flag(Flag::N);
+        self.update_flag(Flag::H, (sp & 0x0F) + (offset as u16 & 0x0F) > 0x0F);
+        self.update_flag(Flag::C, (sp & 0xFF) + offset as u16 > 0xFF);
+        value
+    }
+
+    fn daa(&mut self) {
+        let mut a = self.get_register(&Register::A);
+        let mut carry = self.get_flag(Flag::C);
+        if !self.get_flag(Flag::N) {
+            // Adjust after addition
+            if carry || a > 0x99 {
+                a = a.wrapping_add(0x60);
+                carry = true;
+            }
+            if self.get_flag(Flag::H) || (a & 0x0F) > 0x09 {
+                a = a.wrapping_add(0x06);
+            }
+        } else {
+            // Adjust after subtraction
+            if carry {
+                a = a.wrapping_sub(0x60);
+            }
+            if self.get_flag(Flag::H) {
+                a = a.wrapping_sub(0x06);
+            }
+        }
+        self.set_register(Register::A, a);
+
+        self.check_z(a as u16);
+        self.unset_flag(Flag::H);
+        self.update_flag(Flag::C, carry);
+    }
+
+    // Branch helpers return the extra M-cycles spent when the branch is taken
+
+    fn jr(&mut self, bus: &mut Bus, condition: bool) -> u8 {
+        let offset = self.fetch_data(bus, AddressingMode::R8) as i8;
+        if !condition {
+            return 0;
+        }
+        self.pc = self.pc.wrapping_add(offset as u16);
+        1
     }
 
-    fn call(&mut self, bus: &mut Bus) {
+    fn jp(&mut self, bus: &mut Bus, condition: bool) -> u8 {
         let address = self.fetch_data(bus, AddressingMode::A16);
-        self.push_stack(self.pc);
+        if !condition {
+            return 0;
+        }
         self.pc = address;
+        1
     }
 
-    fn ret(&mut self) {
-        let address = self.pop_stack();
+    fn call(&mut self, bus: &mut Bus, condition: bool) -> u8 {
+        let address = self.fetch_data(bus, AddressingMode::A16);
+        if !condition {
+            return 0;
+        }
+        self.push_stack(bus, self.pc);
         self.pc = address;
+        3
+    }
+
+    fn ret(&mut self, bus: &mut Bus, condition: bool) -> u8 {
+        if !condition {
+            return 0;
+        }
+        let address = self.pop_stack(bus);
+        self.pc = address;
+        3
+    }
+
+    fn rst(&mut self, bus: &mut Bus, address: u16) {
+        self.push_stack(bus, self.pc);
+        self.pc = address;
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::boot::Model;
+    use crate::bus::cartridge;
+
+    // Runs a program from the cartridge entry point until it falls off its end
+    pub(in crate::cpu) fn run(program: &[u8]) -> Cpu {
+        let mut rom = vec![0; 0x8000];
+        rom[0x100..0x100 + program.len()].copy_from_slice(program);
+        rom[0x14D] = rom[0x134..=0x14C]
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1));
+        let mut bus = Bus::new(cartridge::from_bytes(rom).unwrap(), 48000, Model::Dmg);
+        let mut cpu = Cpu::new();
+        cpu.pc = 0x100;
+        while cpu.pc < 0x100 + program.len() as u16 {
+            cpu.cycle(&mut bus);
+        }
+        cpu
+    }
+
+    // A and F after running a program
+    pub(in crate::cpu) fn af(program: &[u8]) -> (u8, u8) {
+        let cpu = run(program);
+        (
+            cpu.get_register(&Register::A),
+            cpu.get_register(&Register::F),
+        )
+    }
+
+    #[test]
+    fn daa_after_add() {
+        // LD A,d8; ADD A,d8; DAA
+        assert_eq!(af(&[0x3E, 0x45, 0xC6, 0x38, 0x27]), (0x83, 0x00));
+        // The half carry out of the low digit is corrected
+        assert_eq!(af(&[0x3E, 0x09, 0xC6, 0x08, 0x27]), (0x17, 0x00));
+        // 99 + 1 wraps to 00 with a carry
+        assert_eq!(af(&[0x3E, 0x99, 0xC6, 0x01, 0x27]), (0x00, 0x90));
+    }
+
+    #[test]
+    fn daa_after_sub() {
+        // LD A,d8; SUB d8; DAA keeps N and clears H
+        assert_eq!(af(&[0x3E, 0x45, 0xD6, 0x38, 0x27]), (0x07, 0x40));
+        // A borrow out of the high digit stays in the carry
+        assert_eq!(af(&[0x3E, 0x10, 0xD6, 0x20, 0x27]), (0x90, 0x50));
+        assert_eq!(af(&[0x3E, 0x12, 0xD6, 0x12, 0x27]), (0x00, 0xC0));
+    }
+
+    #[test]
+    fn add_sp_negative_offset() {
+        // LD SP,d16; ADD SP,-1 carries out of both nibble and byte
+        let cpu = run(&[0x31, 0x0F, 0x00, 0xE8, 0xFF]);
+        assert_eq!(cpu.get_pair(&Register::SP), 0x000E);
+        assert_eq!(cpu.get_register(&Register::F), 0x30);
+        // Going below zero does not set the carry without a low byte carry
+        let cpu = run(&[0x31, 0x00, 0x00, 0xE8, 0xFF]);
+        assert_eq!(cpu.get_pair(&Register::SP), 0xFFFF);
+        assert_eq!(cpu.get_register(&Register::F), 0x00);
+    }
+
+    #[test]
+    fn ld_hl_sp_negative_offset() {
+        // LD SP,d16; LD HL,SP-2
+        let cpu = run(&[0x31, 0x01, 0x10, 0xF8, 0xFE]);
+        assert_eq!(cpu.get_pair(&Register::HL), 0x0FFF);
+        assert_eq!(cpu.get_pair(&Register::SP), 0x1001);
+        assert_eq!(cpu.get_register(&Register::F), 0x00);
+        // LD HL,SP-16 carries out of the low byte only
+        let cpu = run(&[0x31, 0xF2, 0x10, 0xF8, 0xF0]);
+        assert_eq!(cpu.get_pair(&Register::HL), 0x10E2);
+        assert_eq!(cpu.get_register(&Register::F), 0x10);
+    }
+
+    #[test]
+    fn sbc_half_carry_from_carry_in() {
+        // LD A,d8; SCF; SBC A,d8 borrows from the low nibble through the carry
+        assert_eq!(af(&[0x3E, 0x10, 0x37, 0xDE, 0x00]), (0x0F, 0x60));
+        assert_eq!(af(&[0x3E, 0x00, 0x37, 0xDE, 0xFF]), (0x00, 0xF0));
+        // Without the carry in there is nothing to borrow
+        assert_eq!(af(&[0x3E, 0x10, 0xDE, 0x00]), (0x10, 0x40));
     }
 }
@@ -1,8 +1,8 @@
 use super::Cpu;
+use crate::boot::{self, Model};
+use crate::bus::Bus;
 
-const HI: u16 = 0xFF00;
-const LO: u16 = 0x00FF;
-
+#[derive(Copy, Clone)]
 pub enum Register {
     A,
     F,
@@ -16,6 +16,7 @@ pub enum Register {
     BC,
     DE,
     HL,
+    SP,
 }
 
 pub enum Flag {
@@ -25,45 +26,164 @@ pub enum Flag {
     C,
 }
 
+pub struct Registers {
+    a: u8,
+    f: u8,
+    b: u8,
+    c: u8,
+    d: u8,
+    e: u8,
+    h: u8,
+    l: u8,
+    sp: u16,
+}
+
+impl Registers {
+    pub fn new() -> Registers {
+        Registers {
+            a: 0,
+            f: 0,
+            b: 0,
+            c: 0,
+            d: 0,
+            e: 0,
+            h: 0,
+            l: 0,
+            sp: 0,
+        }
+    }
+
+    // Values left behind by the boot ROM of each model, some of them depend
+    // on the cartridge header it checked
+    pub fn post_boot(model: Model, bus: &Bus) -> Registers {
+        // Half carry and carry come from the header checksum computation
+        let f = if bus.read(0x014D) == 0 { 0x80 } else { 0xB0 };
+        match model {
+            Model::Dmg0 => Registers {
+                a: 0x01,
+                f: 0x00,
+                b: 0xFF,
+                c: 0x13,
+                d: 0x00,
+                e: 0xC1,
+                h: 0x84,
+                l: 0x03,
+                sp: 0xFFFE,
+            },
+            Model::Dmg | Model::Mgb => Registers {
+                a: if model == Model::Mgb { 0xFF } else { 0x01 },
+                f,
+                b: 0x00,
+                c: 0x13,
+                d: 0x00,
+                e: 0xD8,
+                h: 0x01,
+                l: 0x4D,
+                sp: 0xFFFE,
+            },
+            Model::Cgb if bus.cgb() => Registers {
+                a: 0x11,
+                f: 0x80,
+                b: 0x00,
+                c: 0x00,
+                d: 0xFF,
+                e: 0x56,
+                h: 0x00,
+                l: 0x0D,
+                sp: 0xFFFE,
+            },
+            Model::Cgb => {
+                // The boot ROM leaves behind the title checksum it computes
+                // for Nintendo games
+                let b = boot::title_checksum(bus).unwrap_or(0x00);
+                let (h, l) = if b == 0x43 || b == 0x58 {
+                    (0x99, 0x1A)
+                } else {
+                    (0x00, 0x7C)
+                };
+                Registers {
+                    a: 0x11,
+                    f: 0x80,
+                    b,
+                    c: 0x00,
+                    d: 0x00,
+                    e: 0x08,
+                    h,
+                    l,
+                    sp: 0xFFFE,
+                }
+            }
+        }
+    }
+}
+
 impl Cpu {
     pub fn get_register(&self, register: &Register) -> u8 {
+        let r = &self.registers;
         match register {
-            Register::A => return ((HI & self.af) >> 8) as u8,
-            Register::F => return (LO & self.af) as u8,
-            Register::B => return ((HI & self.bc) >> 8) as u8,
-            Register::C => return (LO & self.bc) as u8,
-            Register::D => return ((HI & self.de) >> 8) as u8,
-            Register::E => return (LO & self.de) as u8,
-            Register::H => return ((HI & self.hl) >> 8) as u8,
-            Register::L => return (LO & self.hl) as u8,
-            Register::DE => return self.de as u8,
-            Register::HL => return self.hl as u8,
-            _ => panic!("Not a valid register"),
+            Register::A => r.a,
+            Register::F => r.f,
+            Register::B => r.b,
+            Register::C => r.c,
+            Register::D => r.d,
+            Register::E => r.e,
+            Register::H => r.h,
+            Register::L => r.l,
+            _ => panic!("Not an 8-bit register"),
         }
     }
 
     pub fn set_register(&mut self, register: Register, value: u8) {
+        let r = &mut self.registers;
         match register {
-            Register::A => self.af = (value as u16) << 8 | self.get_register(&Register::F) as u16,
-            Register::F => self.af = (self.get_register(&Register::F) as u16) << 8 | (value as u16),
-            Register::B => self.af = (value as u16) << 8 | self.get_register(&Register::C) as u16,
-            Register::C => self.bc = (self.get_register(&Register::B) as u16) << 8 | (value as u16),
-            Register::D => self.de = (value as u16) << 8 | self.get_register(&Register::E) as u16,
-            Register::E => self.bc = (self.get_register(&Register::D) as u16) << 8 | (value as u16),
-            Register::H => self.hl = (value as u16) << 8 | self.get_register(&Register::L) as u16,
-            Register::L => self.bc = (self.get_register(&Register::H) as u16) << 8 | (value as u16),
-            Register::HL => self.hl = value as u16,
-            _ => panic!("Not a valid register"),
+            Register::A => r.a = value,
+            // The lower nibble of F is hardwired to zero
+            Register::F => r.f = value & 0xF0,
+            Register::B => r.b = value,
+            Register::C => r.c = value,
+            Register::D => r.d = value,
+            Register::E => r.e = value,
+            Register::H => r.h = value,
+            Register::L => r.l = value,
+            _ => panic!("Not an 8-bit register"),
+        }
+    }
+
+    pub fn get_pair(&self, register: &Register) -> u16 {
+        let r = &self.registers;
+        match register {
+            Register::AF => u16::from_be_bytes([r.a, r.f]),
+            Register::BC => u16::from_be_bytes([r.b, r.c]),
+            Register::DE => u16::from_be_bytes([r.d, r.e]),
+            Register::HL => u16::from_be_bytes([r.h, r.l]),
+            Register::SP => r.sp,
+            _ => panic!("Not a 16-bit register"),
+        }
+    }
+
+    pub fn set_pair(&mut self, register: Register, value: u16) {
+        let [hi, lo] = value.to_be_bytes();
+        let r = &mut self.registers;
+        match register {
+            Register::AF => {
+                r.a = hi;
+                r.f = lo & 0xF0;
+            }
+            Register::BC => (r.b, r.c) = (hi, lo),
+            Register::DE => (r.d, r.e) = (hi, lo),
+            Register::HL => (r.h, r.l) = (hi, lo),
+            Register::SP => r.sp = value,
+            _ => panic!("Not a 16-bit register"),
         }
     }
 
     pub fn get_flag(&self, flag: Flag) -> bool {
         let f = self.get_register(&Register::F);
         match flag {
-            Flag::Z => return (0b10000000 & f) != 0,
-            Flag::N => return (0b01000000 & f) != 0,
-            Flag::H => return (0b00100000 & f) != 0,
-            Flag::C => return (0b00010000 & f) != 0,
+            Flag::Z => (0b10000000 & f) != 0,
+            Flag::N => (0b01000000 & f) != 0,
+            Flag::H => (0b00100000 & f) != 0,
+            Flag::C => (0b00010000 & f) != 0,
         }
     }
 
@@ -77,6 +197,14 @@ impl Cpu {
         };
     }
 
+    pub fn update_flag(&mut self, flag: Flag, value: bool) {
+        if value {
+            self.set_flag(flag);
+        } else {
+            self.unset_flag(flag);
+        }
+    }
+
     pub fn unset_flag(&mut self, flag: Flag) {
         let f = self.get_register(&Register::F);
         match flag {
@@ -3,7 +3,8 @@ use serde_json;
 use std::collections::HashMap;
 use std::fs;
 
-use crate::bus::rom::Rom;
+use crate::bus::cartridge::Cartridge;
+use crate::error::Result;
 
 pub struct Disassembler {
     opcodes: OpcodeTable,
@@ -11,15 +12,15 @@ pub struct Disassembler {
 }
 
 impl Disassembler {
-    pub fn new() -> Disassembler {
+    pub fn new() -> Result<Disassembler> {
         // Open and parse JSON file of opcodes
-        let json = fs::read_to_string("./opcodes.json").unwrap();
-        let opcodes: OpcodeTable = serde_json::from_str(&json).unwrap();
+        let json = fs::read_to_string("./opcodes.json")?;
+        let opcodes: OpcodeTable = serde_json::from_str(&json)?;
 
-        Disassembler { opcodes, pc: 0x100 }
+        Ok(Disassembler { opcodes, pc: 0x100 })
     }
 
-    pub fn decode_rom(&mut self, rom: &Rom) {
+    pub fn decode_rom(&mut self, rom: &dyn Cartridge) {
         println!("ADDR    HEXA    INSTRUCTION    OPERANDS FLAGS\n");
         for _ in 0..0x10 {
             let byte = rom.read(self.pc);
@@ -29,8 +30,19 @@ impl Disassembler {
         }
     }
 
-    fn read_instruction(&mut self, byte: u8, rom: &Rom) {
-        let opcode = self.opcodes.lookup_opcode(byte);
+    fn read_instruction(&mut self, byte: u8, rom: &dyn Cartridge) {
+        let opcode = if byte == 0xCB {
+            // Prefixed instructions are looked up by the following byte
+            let prefixed = rom.read(self.pc + 1);
+            self.opcodes.lookup_cb_opcode(prefixed)
+        } else {
+            self.opcodes.lookup_opcode(byte)
+        };
+        // Bytes missing from the table are printed as data
+        let Some(opcode) = opcode else {
+            println!("{:04X}    0x{:02X}    {:<11}", self.pc, byte, "DB");
+            return;
+        };
         // Print opcode and associated address
         print!(
             "{:04X}    0x{:02X}    {:<11}    ",
@@ -48,12 +60,12 @@ impl Disassembler {
         }
         // If opcodes has flags print
         let flags = &opcode.flags;
-        for flag in flags.keys().into_iter() {
+        for flag in flags.keys() {
             if flags[flag] != '-' {
                 print!("{} ", flag);
             }
         }
-        print!("\n");
+        println!();
     }
 }
 
@@ -65,15 +77,15 @@ struct OpcodeTable {
 }
 
 impl OpcodeTable {
-    fn lookup_opcode(&self, byte: u8) -> &Opcode {
+    fn lookup_opcode(&self, byte: u8) -> Option<&Opcode> {
         // Look up opcode name from tables
-        let table = &self.unprefixed;
         let hex = format!("0x{:02X}", byte);
-        let opcode = match table.get(&hex) {
-            Some(value) => value,
-            _ => panic!("No opcode like that exists"),
-        };
-        opcode
+        self.unprefixed.get(&hex)
+    }
+
+    fn lookup_cb_opcode(&self, byte: u8) -> Option<&Opcode> {
+        let hex = format!("0x{:02X}", byte);
+        self.cbprefixed.get(&hex)
     }
 }
 
@@ -81,15 +93,5 @@ impl OpcodeTable {
 struct Opcode {
     mnemonic: String,
     bytes: u8,
-    cycles: Vec<u8>,
-    operands: Vec<Operand>,
-    immediate: bool,
     flags: HashMap<char, char>,
 }
-
-#[derive(Debug, Deserialize)]
-struct Operand {
-    name: String,
-    bytes: Option<u8>,
-    immediate: bool,
-}
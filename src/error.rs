@@ -0,0 +1,71 @@
+use std::fmt;
+use std::io;
+
+use crate::gameboy::CLOCK_SPEED;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    // The ROM is too small or its header checksum does not match
+    BadHeader(String),
+    // Cartridge type byte of a bank controller that is not emulated
+    UnsupportedMapper(u8),
+    // A boot ROM whose size does not match the model it is run on
+    BadBootRom { size: usize, expected: usize },
+    // A sample rate of zero or above one sample per T-cycle
+    BadSampleRate(u32),
+    // The CPU locked up after fetching an opcode that does not exist
+    IllegalOpcode { opcode: u8, address: u16 },
+    // The disassembler's opcode table could not be parsed
+    OpcodeTable(serde_json::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "{}", error),
+            Error::BadHeader(reason) => write!(f, "bad cartridge header: {}", reason),
+            Error::UnsupportedMapper(cartridge_type) => {
+                write!(f, "unsupported cartridge type {:#04X}", cartridge_type)
+            }
+            Error::BadBootRom { size, expected } => {
+                write!(f, "boot ROM is {} bytes, expected {}", size, expected)
+            }
+            Error::BadSampleRate(sample_rate) => {
+                write!(
+                    f,
+                    "sample rate must be between 1 and {} Hz, got {}",
+                    CLOCK_SPEED, sample_rate
+                )
+            }
+            Error::IllegalOpcode { opcode, address } => {
+                write!(f, "illegal opcode {:#04X} at {:#06X}", opcode, address)
+            }
+            Error::OpcodeTable(error) => write!(f, "invalid opcode table: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            Error::OpcodeTable(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::OpcodeTable(error)
+    }
+}
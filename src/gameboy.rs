@@ -1,35 +1,256 @@
-use crate::bus::rom::Rom;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use crate::boot::{self, Model};
+use crate::bus::cartridge::{self, Cartridge, CartridgeHeader};
 use crate::bus::Bus;
 use crate::cpu::Cpu;
-use crate::ppu::Ppu;
+use crate::error::{Error, Result};
+use crate::joypad::Button;
+use crate::ppu::{Frame, FrameRgb555, Ppu, Renderer, Tile};
+use crate::serial::SerialLink;
+
+// T-cycles per second of the DMG master clock
+pub const CLOCK_SPEED: u32 = 4_194_304;
+// T-cycles it takes the PPU to draw one full frame
+pub const CYCLES_PER_FRAME: u32 = 70_224;
+// T-cycles between writes of battery-backed RAM to the save file
+const SAVE_INTERVAL: u32 = CLOCK_SPEED;
+
+// Options picked when the emulator is created
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub renderer: Renderer,
+    // Rate in Hz at which the APU produces stereo samples, from 1 up to
+    // CLOCK_SPEED
+    pub sample_rate: u32,
+    // Hardware to run on, CGB-only games always use the CGB. Monochrome
+    // games on a CGB are colored through its compatibility palettes
+    pub model: Model,
+    // Boot ROM to run before the cartridge, 256 bytes or 2304 bytes on CGB
+    // as given by Model::boot_rom_size. The boot sequence is skipped without
+    // one
+    pub boot_rom: Option<Vec<u8>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            renderer: Renderer::Scanline,
+            sample_rate: 48_000,
+            model: Model::Dmg,
+            boot_rom: None,
+        }
+    }
+}
 
 pub struct Gameboy {
     bus: Bus,
     cpu: Cpu,
     ppu: Ppu,
+    frame_cycles: u32,
+    // Save file next to the ROM, only used for cartridges with a battery
+    save_path: Option<PathBuf>,
+    save_cycles: u32,
 }
 
 impl Gameboy {
-    pub fn new(path: &str) -> Gameboy {
-        let mut rom = Rom::new();
-        rom.load_rom(path).unwrap();
+    pub fn new(path: &str) -> Result<Gameboy> {
+        Self::with_config(path, Config::default())
+    }
+
+    // Loads a ROM from a file, cartridges with a battery keep their save in
+    // a .sav file next to it
+    pub fn with_config(path: &str, config: Config) -> Result<Gameboy> {
+        let mut cartridge = cartridge::load(path)?;
+
+        let save_path = if cartridge.header().has_battery() {
+            let save_path = PathBuf::from(path).with_extension("sav");
+            match fs::read(&save_path) {
+                Ok(data) => cartridge.load_ram(&data),
+                Err(error) if error.kind() == ErrorKind::NotFound => (),
+                Err(error) => return Err(Error::Io(error)),
+            }
+            Some(save_path)
+        } else {
+            None
+        };
+        Self::with_cartridge(cartridge, config, save_path)
+    }
+
+    pub fn from_bytes(rom: Vec<u8>) -> Result<Gameboy> {
+        Self::from_bytes_with_config(rom, Config::default())
+    }
+
+    // Loads a ROM from memory, saves are left to the host through
+    // save_ram and load_ram
+    pub fn from_bytes_with_config(rom: Vec<u8>, config: Config) -> Result<Gameboy> {
+        let cartridge = cartridge::from_bytes(rom)?;
+        Self::with_cartridge(cartridge, config, None)
+    }
 
-        let bus = Bus::new(rom);
-        let gb = Gameboy {
+    fn with_cartridge(
+        cartridge: Box<dyn Cartridge>,
+        config: Config,
+        save_path: Option<PathBuf>,
+    ) -> Result<Gameboy> {
+        // Games that only work on a CGB always run on one, dual-mode games
+        // follow the configured model
+        let model = if cartridge.header().requires_cgb() {
+            Model::Cgb
+        } else {
+            config.model
+        };
+        if !(1..=CLOCK_SPEED).contains(&config.sample_rate) {
+            return Err(Error::BadSampleRate(config.sample_rate));
+        }
+        let mut bus = Bus::new(cartridge, config.sample_rate, model);
+        let cpu = match config.boot_rom {
+            Some(boot_rom) => {
+                let expected = model.boot_rom_size();
+                if boot_rom.len() != expected {
+                    return Err(Error::BadBootRom {
+                        size: boot_rom.len(),
+                        expected,
+                    });
+                }
+                bus.load_boot_rom(boot_rom);
+                Cpu::new()
+            }
+            None => {
+                boot::skip(&mut bus, model);
+                Cpu::post_boot(model, &bus)
+            }
+        };
+        Ok(Gameboy {
             bus,
-            cpu: Cpu::new(),
-            ppu: Ppu::new(),
+            cpu,
+            ppu: Ppu::with_renderer(config.renderer),
+            frame_cycles: 0,
+            save_path,
+            save_cycles: 0,
+        })
+    }
+
+    // Runs a single CPU instruction and advances every other component by
+    // the same amount of time, returns the number of T-cycles that passed at
+    // the normal speed clock
+    pub fn cycle(&mut self) -> u32 {
+        // VRAM DMA halts the CPU while the rest of the system keeps running
+        let cycles = self.cpu.cycle(&mut self.bus) as u32 * 4 + self.bus.take_hdma_cycles();
+        // In double speed mode the PPU keeps its pace while the CPU runs twice
+        // as many cycles
+        let dots = if self.bus.double_speed() {
+            cycles / 2
+        } else {
+            cycles
         };
-        gb
+        self.bus.tick(cycles);
+        self.ppu.cycle(&mut self.bus, dots);
+
+        // Saves are written periodically and when the game disables RAM,
+        // a failed write is retried at the next interval
+        self.save_cycles += dots;
+        if self.bus.take_ram_disabled() || self.save_cycles >= SAVE_INTERVAL {
+            self.save_cycles = 0;
+            let _ = self.flush_save();
+        }
+        dots
+    }
+
+    // Runs until a frame's worth of cycles has passed, leftover cycles are
+    // carried over into the next frame
+    pub fn run_frame(&mut self) {
+        while self.frame_cycles < CYCLES_PER_FRAME {
+            self.frame_cycles += self.cycle();
+        }
+        self.frame_cycles -= CYCLES_PER_FRAME;
+    }
+
+    // Contents of the cartridge's save file, battery-backed RAM followed by
+    // the RTC state on MBC3 cartridges with a clock
+    pub fn save_ram(&self) -> Vec<u8> {
+        self.bus.get_rom().save_ram()
+    }
+
+    pub fn load_ram(&mut self, data: &[u8]) {
+        self.bus.load_ram(data);
     }
 
-    pub fn cycle(&mut self) {
-        loop {
-            self.cpu.cycle(&mut self.bus);
+    // Illegal opcode the CPU locked up on, the emulator keeps running but
+    // the CPU stays frozen
+    pub fn locked_up(&self) -> Option<Error> {
+        self.cpu.locked_up()
+    }
+
+    // Writes battery-backed RAM to the save file if it changed since the
+    // last write
+    pub fn flush_save(&mut self) -> Result<()> {
+        if let Some(save_path) = &self.save_path {
+            if self.bus.ram_dirty() {
+                fs::write(save_path, self.bus.get_rom().save_ram())?;
+                self.bus.mark_ram_saved();
+            }
         }
+        Ok(())
+    }
+
+    // Moves generated audio into the buffer as interleaved left and right
+    // samples from -1.0 to 1.0, returns the number of values written
+    pub fn drain_samples(&mut self, out: &mut [f32]) -> usize {
+        self.bus.apu_mut().drain_samples(out)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.bus.apu().sample_rate()
+    }
+
+    // Plugs a link cable into the serial port, replacing the current one
+    pub fn connect_serial(&mut self, link: Box<dyn SerialLink>) {
+        self.bus.connect_serial(link);
+    }
+
+    pub fn press(&mut self, button: Button) {
+        self.bus.press(button);
+    }
+
+    pub fn release(&mut self, button: Button) {
+        self.bus.release(button);
+    }
+
+    // Shades of the last frame drawn, from 0 (white) to 3 (black). Colors
+    // are turned into the closest shade when running on a CGB
+    pub fn frame(&self) -> &Frame {
+        self.ppu.frame()
+    }
+
+    // RGB555 colors of the last frame drawn
+    pub fn frame_rgb555(&self) -> &FrameRgb555 {
+        self.ppu.frame_rgb555()
+    }
+
+    // Tiles decoded from VRAM for tile viewers, bank 1 follows bank 0
+    pub fn tile_set(&self) -> &[Tile] {
+        self.ppu.tile_set()
     }
 
-    pub fn get_rom(&self) -> &Rom {
+    pub fn header(&self) -> &CartridgeHeader {
+        self.bus.get_rom().header()
+    }
+
+    // State of the rumble motor on MBC5 rumble cartridges
+    pub fn rumble(&self) -> bool {
+        self.bus.get_rom().rumble()
+    }
+
+    pub fn get_rom(&self) -> &dyn Cartridge {
         self.bus.get_rom()
     }
 }
+
+impl Drop for Gameboy {
+    fn drop(&mut self) {
+        let _ = self.flush_save();
+    }
+}
@@ -0,0 +1,41 @@
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Interrupt {
+    VBlank,
+    LcdStat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+// Interrupts ordered from highest to lowest priority
+pub const INTERRUPTS: [Interrupt; 5] = [
+    Interrupt::VBlank,
+    Interrupt::LcdStat,
+    Interrupt::Timer,
+    Interrupt::Serial,
+    Interrupt::Joypad,
+];
+
+impl Interrupt {
+    // Bit used for the interrupt in the IE and IF registers
+    pub fn mask(self) -> u8 {
+        match self {
+            Interrupt::VBlank => 0x01,
+            Interrupt::LcdStat => 0x02,
+            Interrupt::Timer => 0x04,
+            Interrupt::Serial => 0x08,
+            Interrupt::Joypad => 0x10,
+        }
+    }
+
+    // Address the CPU jumps to when servicing the interrupt
+    pub fn vector(self) -> u16 {
+        match self {
+            Interrupt::VBlank => 0x40,
+            Interrupt::LcdStat => 0x48,
+            Interrupt::Timer => 0x50,
+            Interrupt::Serial => 0x58,
+            Interrupt::Joypad => 0x60,
+        }
+    }
+}
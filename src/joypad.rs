@@ -0,0 +1,94 @@
+pub const P1: u16 = 0xFF00;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+impl Button {
+    // Directions are kept in the lower nibble of the pressed buttons and
+    // actions in the upper one, matching their P1 bits within each row
+    fn mask(self) -> u8 {
+        match self {
+            Button::Right => 0x01,
+            Button::Left => 0x02,
+            Button::Up => 0x04,
+            Button::Down => 0x08,
+            Button::A => 0x10,
+            Button::B => 0x20,
+            Button::Select => 0x40,
+            Button::Start => 0x80,
+        }
+    }
+}
+
+pub struct Joypad {
+    pressed: u8,
+    // P1 bits 4 and 5, a 0 selects the direction and action row respectively
+    select: u8,
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Joypad {
+    pub fn new() -> Joypad {
+        Joypad {
+            pressed: 0,
+            select: 0x30,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        // Inputs are active low, unused upper bits read as set
+        0xC0 | self.select | (!self.lines() & 0x0F)
+    }
+
+    // Writes only select rows, returns true if the joypad interrupt should
+    // be requested
+    pub fn write(&mut self, data: u8) -> bool {
+        self.update(|joypad| joypad.select = data & 0x30)
+    }
+
+    pub fn press(&mut self, button: Button) -> bool {
+        self.update(|joypad| joypad.pressed |= button.mask())
+    }
+
+    pub fn release(&mut self, button: Button) -> bool {
+        self.update(|joypad| joypad.pressed &= !button.mask())
+    }
+
+    // True while any input line of a selected row is pulled low
+    pub fn input_low(&self) -> bool {
+        self.lines() != 0
+    }
+
+    // Applies a change and reports whether any input line went from high to low
+    fn update(&mut self, change: impl FnOnce(&mut Joypad)) -> bool {
+        let old = self.lines();
+        change(self);
+        self.lines() & !old != 0
+    }
+
+    // Pressed buttons of the selected rows, with the rows ORed together
+    fn lines(&self) -> u8 {
+        let mut lines = 0;
+        if self.select & 0x10 == 0 {
+            lines |= self.pressed & 0x0F;
+        }
+        if self.select & 0x20 == 0 {
+            lines |= self.pressed >> 4;
+        }
+        lines
+    }
+}
@@ -1,6 +1,15 @@
+pub mod apu;
+pub mod boot;
 pub mod bus;
 pub mod cpu;
+pub mod error;
 pub mod gameboy;
+pub mod interrupt;
+pub mod joypad;
 pub mod ppu;
+pub mod serial;
+pub mod timer;
 
 pub mod disassembler;
+
+pub use error::{Error, Result};
@@ -1,15 +1,182 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
 // use gameboy_emulator::disassembler::Disassembler;
-use gameboy_emulator::gameboy::Gameboy;
+use gameboy_emulator::boot::Model;
+use gameboy_emulator::gameboy::{Config, Gameboy, CLOCK_SPEED, CYCLES_PER_FRAME};
+use gameboy_emulator::Error;
+
+// Set by SIGINT and SIGTERM so the main loop can stop and flush the save
+static QUIT: AtomicBool = AtomicBool::new(false);
+
+const USAGE: &str = "usage: gameboy-emulator [ROM] [--wav FILE] [--frames COUNT] [--boot-rom FILE] [--model dmg0|dmg|mgb|cgb]";
+
+struct Args {
+    rom: String,
+    // Runs headless as fast as possible and writes the audio to a WAV file
+    wav: Option<String>,
+    frames: u32,
+    boot_rom: Option<String>,
+    model: Model,
+}
 
-fn main() {
-    let rom = "test/tetris.gb";
-    let mut gameboy = Gameboy::new(rom);
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        rom: String::from("test/tetris.gb"),
+        wav: None,
+        frames: 600,
+        boot_rom: None,
+        model: Model::Dmg,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--wav" => args.wav = Some(iter.next().ok_or("--wav needs a file name")?),
+            "--frames" => {
+                let count = iter.next().ok_or("--frames needs a count")?;
+                args.frames = count
+                    .parse()
+                    .map_err(|_| format!("invalid frame count {}", count))?;
+            }
+            "--boot-rom" => {
+                args.boot_rom = Some(iter.next().ok_or("--boot-rom needs a file name")?)
+            }
+            "--model" => {
+                args.model = match iter.next().as_deref() {
+                    Some("dmg0") => Model::Dmg0,
+                    Some("dmg") => Model::Dmg,
+                    Some("mgb") => Model::Mgb,
+                    Some("cgb") => Model::Cgb,
+                    Some(model) => return Err(format!("unknown model {}", model)),
+                    None => return Err(String::from("--model needs a model name")),
+                }
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ => args.rom = arg,
+        }
+    }
+    Ok(args)
+}
+
+fn main() -> Result<(), Error> {
+    let args = parse_args().unwrap_or_else(|error| {
+        eprintln!("{}\n{}", error, USAGE);
+        process::exit(2);
+    });
+    let config = Config {
+        model: args.model,
+        boot_rom: args.boot_rom.map(fs::read).transpose()?,
+        ..Config::default()
+    };
+    let mut gameboy = Gameboy::with_config(&args.rom, config)?;
 
     // Disassemble and decode rom
-    // let mut disassembler = Disassembler::new();
+    // let mut disassembler = Disassembler::new()?;
     // let rom_data = gameboy.get_rom();
     // disassembler.decode_rom(rom_data);
 
-    // Main loop
-    gameboy.cycle();
+    if let Some(wav) = &args.wav {
+        return record_audio(&mut gameboy, wav, args.frames);
+    }
+
+    // Main loop, paced to run frames at the speed of real hardware
+    let frame_time = Duration::from_secs_f64(CYCLES_PER_FRAME as f64 / CLOCK_SPEED as f64);
+    let mut next_frame = Instant::now();
+    let mut samples = vec![0.0; 4096];
+    handle_quit_signals();
+    while !QUIT.load(Ordering::Relaxed) {
+        gameboy.run_frame();
+        if let Some(error) = gameboy.locked_up() {
+            return Err(error);
+        }
+        // There is no audio output yet, drop the samples
+        while gameboy.drain_samples(&mut samples) > 0 {}
+
+        next_frame += frame_time;
+        if let Some(delay) = next_frame.checked_duration_since(Instant::now()) {
+            thread::sleep(delay);
+        }
+    }
+    gameboy.flush_save()
+}
+
+#[cfg(unix)]
+fn handle_quit_signals() {
+    const SIGINT: i32 = 2;
+    const SIGTERM: i32 = 15;
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+
+    extern "C" fn quit(_signum: i32) {
+        QUIT.store(true, Ordering::Relaxed);
+    }
+
+    for signum in [SIGINT, SIGTERM] {
+        // Only stores to an atomic, which is safe inside a signal handler
+        unsafe {
+            signal(signum, quit);
+        }
+    }
+}
+
+// Closing the console still exits without a final save, the periodic saves
+// keep the loss small
+#[cfg(not(unix))]
+fn handle_quit_signals() {}
+
+// Runs a number of frames without pacing and saves the audio they produced
+fn record_audio(gameboy: &mut Gameboy, path: &str, frames: u32) -> Result<(), Error> {
+    let mut audio = Vec::new();
+    let mut samples = vec![0.0; 4096];
+    for _ in 0..frames {
+        gameboy.run_frame();
+        if let Some(error) = gameboy.locked_up() {
+            return Err(error);
+        }
+        loop {
+            let count = gameboy.drain_samples(&mut samples);
+            if count == 0 {
+                break;
+            }
+            audio.extend_from_slice(&samples[..count]);
+        }
+    }
+    write_wav(path, gameboy.sample_rate(), &audio)?;
+    Ok(())
+}
+
+// Writes interleaved stereo samples as a 16-bit PCM WAV file
+fn write_wav(path: &str, sample_rate: u32, samples: &[f32]) -> std::io::Result<()> {
+    let channels: u16 = 2;
+    let bits: u16 = 16;
+    let block_align = channels * bits / 8;
+    let data_size = samples.len() as u32 * 2;
+
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(b"RIFF")?;
+    file.write_all(&(36 + data_size).to_le_bytes())?;
+    file.write_all(b"WAVE")?;
+    file.write_all(b"fmt ")?;
+    file.write_all(&16u32.to_le_bytes())?;
+    // PCM format
+    file.write_all(&1u16.to_le_bytes())?;
+    file.write_all(&channels.to_le_bytes())?;
+    file.write_all(&sample_rate.to_le_bytes())?;
+    file.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    file.write_all(&block_align.to_le_bytes())?;
+    file.write_all(&bits.to_le_bytes())?;
+    file.write_all(b"data")?;
+    file.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        file.write_all(&value.to_le_bytes())?;
+    }
+    file.flush()
 }
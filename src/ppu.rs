@@ -1,25 +1,659 @@
+use crate::boot::DMG_COLORS;
+use crate::bus::vram::TILES_PER_BANK;
 use crate::bus::Bus;
+use crate::interrupt::Interrupt;
 
-#[derive(Copy, Clone)]
-enum ColorId {
+use self::fifo::Fifo;
+
+mod fifo;
+
+const DOTS_PER_LINE: u16 = 456;
+const OAM_SCAN_DOTS: u16 = 80;
+const DRAWING_DOTS: u16 = 172;
+const VISIBLE_LINES: u8 = 144;
+const LINES_PER_FRAME: u8 = 154;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+const WHITE: u16 = 0x7FFF;
+
+// Shades from 0 (white) to 3 (black) for every pixel on screen
+pub type Frame = [u8; SCREEN_WIDTH * SCREEN_HEIGHT];
+// RGB555 color of every pixel on screen, red in the lowest bits
+pub type FrameRgb555 = [u16; SCREEN_WIDTH * SCREEN_HEIGHT];
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Mode {
+    HBlank,
+    VBlank,
+    OamScan,
+    Drawing,
+}
+
+impl Mode {
+    // Value reported in the lower two bits of STAT
+    fn bits(self) -> u8 {
+        match self {
+            Mode::HBlank => 0,
+            Mode::VBlank => 1,
+            Mode::OamScan => 2,
+            Mode::Drawing => 3,
+        }
+    }
+}
+
+// How mode 3 turns tiles into pixels
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Renderer {
+    // Draws the whole line at once with a fixed length mode 3
+    #[default]
+    Scanline,
+    // Shifts out one pixel per dot through the background fetcher, picking
+    // up register changes made mid-line
+    PixelFifo,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ColorId {
     Zero,
     One,
     Two,
     Three,
 }
 
-type Tile = [[ColorId; 8]; 8];
+impl ColorId {
+    fn from_bits(lo: u8, hi: u8) -> ColorId {
+        match (hi & 1) << 1 | (lo & 1) {
+            0 => ColorId::Zero,
+            1 => ColorId::One,
+            2 => ColorId::Two,
+            _ => ColorId::Three,
+        }
+    }
+
+    fn index(self) -> u8 {
+        match self {
+            ColorId::Zero => 0,
+            ColorId::One => 1,
+            ColorId::Two => 2,
+            ColorId::Three => 3,
+        }
+    }
+
+    // Maps the color id to a shade through a BGP/OBP style palette
+    fn shade(self, palette: u8) -> u8 {
+        (palette >> (self.index() * 2)) & 0x03
+    }
+}
+
+pub type Tile = [[ColorId; 8]; 8];
+
+const MAX_SPRITES_PER_LINE: usize = 10;
+
+// A single object attribute entry from OAM
+#[derive(Copy, Clone)]
+struct Sprite {
+    // Position on screen offset by 16 and 8 pixels respectively
+    y: u8,
+    x: u8,
+    tile: u8,
+    flags: u8,
+    // Position in OAM, breaks ties between sprites on the same X coordinate
+    index: u8,
+    // Sprite height at OAM scan, LCDC may change it before the sprite is drawn
+    height: u8,
+}
+
+impl Sprite {
+    fn behind_background(&self) -> bool {
+        self.flags & 0x80 != 0
+    }
+
+    fn y_flip(&self) -> bool {
+        self.flags & 0x40 != 0
+    }
+
+    fn x_flip(&self) -> bool {
+        self.flags & 0x20 != 0
+    }
+
+    fn uses_obp1(&self) -> bool {
+        self.flags & 0x10 != 0
+    }
+
+    // VRAM bank and color palette, only used in CGB mode
+    fn bank(&self) -> usize {
+        (self.flags >> 3) as usize & 0x01
+    }
+
+    fn palette(&self) -> u8 {
+        self.flags & 0x07
+    }
+}
+
+// Background or window pixel along with the attributes of its tile, which
+// are always 0 outside of CGB mode
+#[derive(Copy, Clone)]
+struct BgPixel {
+    color: ColorId,
+    attributes: u8,
+}
+
+impl BgPixel {
+    // Color 0 on a tile with the given attributes
+    fn blank(attributes: u8) -> BgPixel {
+        BgPixel {
+            color: ColorId::Zero,
+            attributes,
+        }
+    }
+
+    // Tile map attributes use the same layout as sprite flags
+    fn priority(&self) -> bool {
+        self.attributes & 0x80 != 0
+    }
+
+    fn y_flip(&self) -> bool {
+        self.attributes & 0x40 != 0
+    }
+
+    fn x_flip(&self) -> bool {
+        self.attributes & 0x20 != 0
+    }
+
+    fn bank(&self) -> usize {
+        (self.attributes >> 3) as usize & 0x01
+    }
+
+    fn palette(&self) -> u8 {
+        self.attributes & 0x07
+    }
+}
+
+#[derive(Copy, Clone)]
+struct SpritePixel {
+    color: ColorId,
+    sprite: Sprite,
+}
 
 pub struct Ppu {
-    tile_set: [Tile; 384],
+    renderer: Renderer,
+    fifo: Fifo,
+    tile_set: [Tile; TILES_PER_BANK * 2],
+    mode: Mode,
+    // Dots elapsed on the current line
+    dot: u16,
+    // STAT interrupt sources are ORed together and only fire on a rising edge
+    stat_line: bool,
+    lcd_on: bool,
+    // Frame being drawn and the last completed frame, as shades and colors
+    back_buffer: Box<Frame>,
+    front_buffer: Box<Frame>,
+    back_rgb555: Box<FrameRgb555>,
+    front_rgb555: Box<FrameRgb555>,
+    // Line of the window to draw next, only advanced on lines showing it
+    window_line: u8,
+    // Set once LY matched WY during the current frame
+    window_triggered: bool,
+    // Sprites selected during OAM scan for the current line
+    line_sprites: Vec<Sprite>,
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Ppu {
     pub fn new() -> Ppu {
+        Self::with_renderer(Renderer::Scanline)
+    }
+
+    pub fn with_renderer(renderer: Renderer) -> Ppu {
         Ppu {
-            tile_set: [[[ColorId::Zero; 8]; 8]; 384],
+            renderer,
+            fifo: Fifo::new(),
+            tile_set: [[[ColorId::Zero; 8]; 8]; TILES_PER_BANK * 2],
+            mode: Mode::HBlank,
+            dot: 0,
+            stat_line: false,
+            lcd_on: false,
+            back_buffer: Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]),
+            front_buffer: Box::new([0; SCREEN_WIDTH * SCREEN_HEIGHT]),
+            back_rgb555: Box::new([WHITE; SCREEN_WIDTH * SCREEN_HEIGHT]),
+            front_rgb555: Box::new([WHITE; SCREEN_WIDTH * SCREEN_HEIGHT]),
+            window_line: 0,
+            window_triggered: false,
+            line_sprites: Vec::with_capacity(MAX_SPRITES_PER_LINE),
         }
     }
 
-    pub fn cycle(bus: &mut Bus) {}
+    pub fn cycle(&mut self, bus: &mut Bus, cycles: u32) {
+        self.update_tile_set(bus);
+        for _ in 0..cycles {
+            self.step(bus);
+        }
+    }
+
+    pub fn renderer(&self) -> Renderer {
+        self.renderer
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    // Last frame completed by the PPU, CGB colors are turned into the
+    // closest shade
+    pub fn frame(&self) -> &Frame {
+        &self.front_buffer
+    }
+
+    pub fn frame_rgb555(&self) -> &FrameRgb555 {
+        &self.front_rgb555
+    }
+
+    // Advances the PPU by a single dot
+    fn step(&mut self, bus: &mut Bus) {
+        if bus.lcd().lcdc & 0x80 == 0 {
+            if self.lcd_on {
+                self.turn_off(bus);
+            }
+            return;
+        }
+        if !self.lcd_on {
+            self.turn_on(bus);
+        }
+
+        self.dot += 1;
+        match self.mode {
+            Mode::OamScan if self.dot == OAM_SCAN_DOTS => {
+                self.scan_oam(bus);
+                if bus.lcd().ly == bus.lcd().wy {
+                    self.window_triggered = true;
+                }
+                self.fifo.start_line(bus.lcd().scx);
+                self.set_mode(bus, Mode::Drawing);
+            }
+            Mode::Drawing => {
+                // The pixel FIFO stretches mode 3 by fine scrolling, the
+                // window and sprites, cutting into HBlank
+                let done = match self.renderer {
+                    Renderer::Scanline => {
+                        let done = self.dot == OAM_SCAN_DOTS + DRAWING_DOTS;
+                        if done {
+                            self.render_line(bus);
+                        }
+                        done
+                    }
+                    Renderer::PixelFifo => self.fifo_step(bus),
+                };
+                if done {
+                    self.set_mode(bus, Mode::HBlank);
+                    bus.hblank();
+                }
+            }
+            Mode::HBlank if self.dot == DOTS_PER_LINE => {
+                let ly = self.next_line(bus);
+                if ly == VISIBLE_LINES {
+                    self.set_mode(bus, Mode::VBlank);
+                    bus.request_interrupt(Interrupt::VBlank);
+                    std::mem::swap(&mut self.back_buffer, &mut self.front_buffer);
+                    std::mem::swap(&mut self.back_rgb555, &mut self.front_rgb555);
+                } else {
+                    self.set_mode(bus, Mode::OamScan);
+                }
+            }
+            Mode::VBlank if self.dot == DOTS_PER_LINE => {
+                let ly = self.next_line(bus);
+                if ly == LINES_PER_FRAME {
+                    bus.lcd_mut().ly = 0;
+                    self.start_frame();
+                    self.set_mode(bus, Mode::OamScan);
+                }
+            }
+            _ => (),
+        }
+        self.update_stat(bus);
+    }
+
+    fn next_line(&mut self, bus: &mut Bus) -> u8 {
+        self.dot = 0;
+        let lcd = bus.lcd_mut();
+        lcd.ly += 1;
+        lcd.ly
+    }
+
+    fn set_mode(&mut self, bus: &mut Bus, mode: Mode) {
+        self.mode = mode;
+        let lcd = bus.lcd_mut();
+        lcd.stat = (lcd.stat & !0x03) | mode.bits();
+    }
+
+    // Updates the LY=LYC flag and requests a STAT interrupt if any of the
+    // selected sources became active
+    fn update_stat(&mut self, bus: &mut Bus) {
+        let lcd = bus.lcd_mut();
+        let coincidence = lcd.ly == lcd.lyc;
+        if coincidence {
+            lcd.stat |= 0x04;
+        } else {
+            lcd.stat &= !0x04;
+        }
+
+        let stat = lcd.stat;
+        let line = (coincidence && stat & 0x40 != 0)
+            || match self.mode {
+                Mode::HBlank => stat & 0x08 != 0,
+                Mode::VBlank => stat & 0x10 != 0,
+                Mode::OamScan => stat & 0x20 != 0,
+                Mode::Drawing => false,
+            };
+        if line && !self.stat_line {
+            bus.request_interrupt(Interrupt::LcdStat);
+        }
+        self.stat_line = line;
+    }
+
+    fn turn_off(&mut self, bus: &mut Bus) {
+        // The LCD idles in HBlank on line 0 while switched off
+        self.lcd_on = false;
+        self.dot = 0;
+        self.stat_line = false;
+        bus.lcd_mut().ly = 0;
+        self.set_mode(bus, Mode::HBlank);
+        // A disabled LCD shows a blank white screen
+        self.front_buffer.fill(0);
+        self.front_rgb555.fill(WHITE);
+    }
+
+    fn turn_on(&mut self, bus: &mut Bus) {
+        // Drawing restarts from the top of the screen
+        self.lcd_on = true;
+        self.dot = 0;
+        bus.lcd_mut().ly = 0;
+        self.start_frame();
+        self.set_mode(bus, Mode::OamScan);
+        self.update_stat(bus);
+    }
+
+    fn draw(&mut self, index: usize, color: u16) {
+        self.back_buffer[index] = shade(color);
+        self.back_rgb555[index] = color;
+    }
+
+    fn start_frame(&mut self) {
+        self.window_line = 0;
+        self.window_triggered = false;
+    }
+
+    // Draws the background and window for the current line into the back buffer
+    fn render_line(&mut self, bus: &Bus) {
+        let lcd = bus.lcd();
+        let offset = lcd.ly as usize * SCREEN_WIDTH;
+        let pixels = self.background_line(bus);
+        let sprites_enabled = lcd.lcdc & 0x02 != 0;
+        for (x, background) in pixels.into_iter().enumerate() {
+            let sprite = if sprites_enabled {
+                self.sprite_pixel(bus, x as u8)
+            } else {
+                None
+            };
+            self.draw(offset + x, mix_pixel(bus, background, sprite));
+        }
+    }
+
+    // Selects the first 10 sprites in OAM that overlap the current line, then
+    // orders them by drawing priority
+    fn scan_oam(&mut self, bus: &Bus) {
+        let ly = bus.lcd().ly as i16;
+        let height = self.sprite_height(bus);
+        let oam = bus.oam();
+
+        self.line_sprites.clear();
+        for index in 0..40 {
+            let address = index * 4;
+            let y = oam.read(address);
+            let top = y as i16 - 16;
+            if ly < top || ly >= top + height as i16 {
+                continue;
+            }
+            self.line_sprites.push(Sprite {
+                y,
+                x: oam.read(address + 1),
+                tile: oam.read(address + 2),
+                flags: oam.read(address + 3),
+                index: index as u8,
+                height,
+            });
+            if self.line_sprites.len() == MAX_SPRITES_PER_LINE {
+                break;
+            }
+        }
+        // On DMG the sprite with the smaller X wins, then the one earlier in OAM
+        self.line_sprites
+            .sort_by_key(|sprite| (sprite.x, sprite.index));
+    }
+
+    fn sprite_height(&self, bus: &Bus) -> u8 {
+        if bus.lcd().lcdc & 0x04 != 0 {
+            16
+        } else {
+            8
+        }
+    }
+
+    // Finds the highest priority non-transparent sprite pixel at X, in CGB
+    // mode the sprite earliest in OAM wins regardless of X
+    fn sprite_pixel(&self, bus: &Bus, x: u8) -> Option<SpritePixel> {
+        let ly = bus.lcd().ly;
+        let cgb = bus.cgb();
+        let mut found: Option<SpritePixel> = None;
+        for sprite in &self.line_sprites {
+            let left = sprite.x as i16 - 8;
+            if (x as i16) < left || x as i16 >= left + 8 {
+                continue;
+            }
+
+            let mut row = ly.wrapping_sub(sprite.y.wrapping_sub(16));
+            let mut column = (x as i16 - left) as u8;
+            if sprite.y_flip() {
+                row = sprite.height - 1 - row;
+            }
+            if sprite.x_flip() {
+                column = 7 - column;
+            }
+            // 8x16 sprites ignore the lowest bit of the tile number
+            let mut tile = match sprite.height {
+                16 => (sprite.tile & 0xFE) as usize + row as usize / 8,
+                _ => sprite.tile as usize,
+            };
+            if cgb {
+                tile += sprite.bank() * TILES_PER_BANK;
+            }
+
+            let color = self.tile_set[tile][row as usize % 8][column as usize];
+            if color == ColorId::Zero {
+                continue;
+            }
+            let pixel = SpritePixel {
+                color,
+                sprite: *sprite,
+            };
+            if !cgb {
+                return Some(pixel);
+            }
+            if found.is_none_or(|found| sprite.index < found.sprite.index) {
+                found = Some(pixel);
+            }
+        }
+        found
+    }
+
+    // Background and window pixels on the current line
+    fn background_line(&mut self, bus: &Bus) -> [BgPixel; SCREEN_WIDTH] {
+        let lcd = bus.lcd();
+        let mut pixels = [BgPixel::blank(0); SCREEN_WIDTH];
+
+        // With BG and window disabled the line stays blank, in CGB mode they
+        // only lose their priority over sprites
+        if lcd.lcdc & 0x01 == 0 && !bus.cgb() {
+            return pixels;
+        }
+
+        // The window is placed at WX - 7, values below 7 push it off the left edge
+        let window_x = lcd.wx as i16 - 7;
+        let window_visible =
+            lcd.lcdc & 0x20 != 0 && self.window_triggered && (lcd.wx as usize) < SCREEN_WIDTH + 7;
+        let bg_map = if lcd.lcdc & 0x08 != 0 { 0x1C00 } else { 0x1800 };
+        let window_map = if lcd.lcdc & 0x40 != 0 { 0x1C00 } else { 0x1800 };
+
+        for (x, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = if window_visible && x as i16 >= window_x {
+                let window_x = (x as i16 - window_x) as u8;
+                self.tile_pixel(bus, window_map, window_x, self.window_line)
+            } else {
+                let bg_x = lcd.scx.wrapping_add(x as u8);
+                let bg_y = lcd.scy.wrapping_add(lcd.ly);
+                self.tile_pixel(bus, bg_map, bg_x, bg_y)
+            };
+        }
+
+        if window_visible {
+            self.window_line += 1;
+        }
+        pixels
+    }
+
+    // Looks up a pixel in a 256x256 tile map, in CGB mode the attributes
+    // for each tile are stored at the same address in VRAM bank 1
+    fn tile_pixel(&self, bus: &Bus, map: u16, x: u8, y: u8) -> BgPixel {
+        let vram = bus.vram();
+        let address = map + (y as u16 / 8) * 32 + x as u16 / 8;
+        let attributes = if bus.cgb() {
+            vram.read_bank(1, address)
+        } else {
+            0
+        };
+        let mut pixel = BgPixel::blank(attributes);
+        let tile = self.tile_index(bus, vram.read_bank(0, address));
+        let mut row = y as usize % 8;
+        let mut column = x as usize % 8;
+        if pixel.y_flip() {
+            row = 7 - row;
+        }
+        if pixel.x_flip() {
+            column = 7 - column;
+        }
+        pixel.color = self.tile_set[pixel.bank() * TILES_PER_BANK + tile][row][column];
+        pixel
+    }
+
+    // Resolves a tile number from a tile map into an index into the tile set,
+    // following the addressing mode selected by LCDC bit 4
+    fn tile_index(&self, bus: &Bus, tile: u8) -> usize {
+        if bus.lcd().lcdc & 0x10 != 0 || tile >= 128 {
+            // 0x8000 addressing, tiles 128-255 are shared by both modes
+            tile as usize
+        } else {
+            // 0x8800 addressing, tiles 0-127 come from 0x9000
+            256 + tile as usize
+        }
+    }
+
+    // Tiles decoded from 0x8000-0x97FF, indexed from the start of VRAM with
+    // the tiles of bank 1 following those of bank 0
+    pub fn tile_set(&self) -> &[Tile; TILES_PER_BANK * 2] {
+        &self.tile_set
+    }
+
+    // Re-decodes the tiles that were written to since the last update
+    fn update_tile_set(&mut self, bus: &mut Bus) {
+        let vram = bus.vram_mut();
+        if !vram.is_dirty() {
+            return;
+        }
+        let dirty: Vec<usize> = vram.take_dirty_tiles().collect();
+        for index in dirty {
+            self.tile_set[index] = decode_tile(bus, index);
+        }
+    }
+}
+
+// Tiles are stored as 8 rows of two bytes, the first byte holds the low bit
+// of each pixel's color id and the second byte the high bit
+fn decode_tile(bus: &Bus, index: usize) -> Tile {
+    let vram = bus.vram();
+    let bank = index / TILES_PER_BANK;
+    let mut tile = [[ColorId::Zero; 8]; 8];
+    for (y, row) in tile.iter_mut().enumerate() {
+        let address = ((index % TILES_PER_BANK) * 16 + y * 2) as u16;
+        let lo = vram.read_bank(bank, address);
+        let hi = vram.read_bank(bank, address + 1);
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let bit = 7 - x;
+            *pixel = ColorId::from_bits(lo >> bit, hi >> bit);
+        }
+    }
+    tile
+}
+
+// Picks the background or sprite pixel drawn at a position and resolves it
+// to its RGB555 color
+fn mix_pixel(bus: &Bus, background: BgPixel, sprite: Option<SpritePixel>) -> u16 {
+    let lcd = bus.lcd();
+    let cgb = bus.cgb();
+    // Outside of CGB mode disabling BG and window leaves only color 0
+    let bg_color = if cgb || lcd.lcdc & 0x01 != 0 {
+        background.color
+    } else {
+        ColorId::Zero
+    };
+
+    if let Some(pixel) = sprite.filter(|pixel| pixel.color != ColorId::Zero) {
+        // BG colors 1-3 cover sprites behind the background, in CGB mode also
+        // those on tiles with the priority attribute unless LCDC bit 0 is clear
+        let hidden = bg_color != ColorId::Zero
+            && if cgb {
+                lcd.lcdc & 0x01 != 0 && (background.priority() || pixel.sprite.behind_background())
+            } else {
+                pixel.sprite.behind_background()
+            };
+        if !hidden {
+            let sprite = pixel.sprite;
+            let palette = if sprite.uses_obp1() {
+                lcd.obp1
+            } else {
+                lcd.obp0
+            };
+            return if cgb {
+                bus.obj_palettes()
+                    .color(sprite.palette(), pixel.color.index())
+            } else if bus.dmg_compat() {
+                bus.obj_palettes()
+                    .color(sprite.uses_obp1() as u8, pixel.color.shade(palette))
+            } else {
+                DMG_COLORS[pixel.color.shade(palette) as usize]
+            };
+        }
+    }
+
+    if cgb {
+        bus.bg_palettes()
+            .color(background.palette(), bg_color.index())
+    } else if bus.dmg_compat() {
+        bus.bg_palettes().color(0, bg_color.shade(lcd.bgp))
+    } else {
+        DMG_COLORS[bg_color.shade(lcd.bgp) as usize]
+    }
+}
+
+// Shade closest to the brightness of an RGB555 color, the monochrome shades
+// map back to themselves
+fn shade(color: u16) -> u8 {
+    let channel = |shift: u16| ((color >> shift) & 0x1F) as u32;
+    let luma = (channel(0) * 3 + channel(5) * 6 + channel(10)) / 10;
+    (3 - (luma * 3 + 15) / 31) as u8
 }
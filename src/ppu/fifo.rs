@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+
+use crate::bus::Bus;
+
+use super::{mix_pixel, BgPixel, ColorId, Ppu, Sprite, SpritePixel, SCREEN_WIDTH};
+
+// Mode 3 lasts at least 172 dots, the first tile fetched on a line is thrown
+// away before the fetcher starts over for the pixels that are drawn
+const STARTUP_DOTS: u8 = 5;
+// Dots the pixel output is paused while the tile data of a sprite is read
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum FetchStep {
+    Tile,
+    DataLow,
+    DataHigh,
+    Push,
+}
+
+// State of the background fetcher and the two pixel FIFOs for the current line
+pub(super) struct Fifo {
+    background: VecDeque<BgPixel>,
+    sprites: VecDeque<SpritePixel>,
+    step: FetchStep,
+    // Every step but pushing takes two dots
+    step_dot: u8,
+    // Tile column to fetch next, counted from SCX or the left edge of the window
+    fetch_x: u8,
+    tile: u8,
+    attributes: u8,
+    lo: u8,
+    hi: u8,
+    // Pixels sent to the LCD on the current line
+    lx: u8,
+    // Pixels dropped from the FIFO before drawing, SCX fine scroll or WX < 7
+    discard: u8,
+    // Dots left until pixels are shifted out again
+    stall: u8,
+    window: bool,
+    // Position in the line's sprites of the next one to fetch
+    next_sprite: usize,
+}
+
+impl Fifo {
+    pub(super) fn new() -> Fifo {
+        Fifo {
+            background: VecDeque::with_capacity(16),
+            sprites: VecDeque::with_capacity(8),
+            step: FetchStep::Tile,
+            step_dot: 0,
+            fetch_x: 0,
+            tile: 0,
+            attributes: 0,
+            lo: 0,
+            hi: 0,
+            lx: 0,
+            discard: 0,
+            stall: 0,
+            window: false,
+            next_sprite: 0,
+        }
+    }
+
+    pub(super) fn start_line(&mut self, scx: u8) {
+        self.background.clear();
+        self.sprites.clear();
+        self.restart_fetch();
+        self.lx = 0;
+        self.discard = scx % 8;
+        self.stall = STARTUP_DOTS;
+        self.window = false;
+        self.next_sprite = 0;
+    }
+
+    fn restart_fetch(&mut self) {
+        self.step = FetchStep::Tile;
+        self.step_dot = 0;
+        self.fetch_x = 0;
+    }
+
+    fn push_tile(&mut self) {
+        for column in 0..8 {
+            let pixel = BgPixel::blank(self.attributes);
+            let bit = if pixel.x_flip() { column } else { 7 - column };
+            self.background.push_back(BgPixel {
+                color: ColorId::from_bits(self.lo >> bit, self.hi >> bit),
+                ..pixel
+            });
+        }
+        self.step = FetchStep::Tile;
+        self.fetch_x = self.fetch_x.wrapping_add(1);
+    }
+}
+
+impl Ppu {
+    // Advances mode 3 by a single dot, returns true once all 160 pixels of
+    // the line have been drawn
+    pub(super) fn fifo_step(&mut self, bus: &Bus) -> bool {
+        if self.fifo.stall > 0 {
+            self.fifo.stall -= 1;
+            return false;
+        }
+
+        let lcd = bus.lcd();
+        // Reaching WX - 7 throws away the background pixels and restarts the
+        // fetcher on the window
+        if !self.fifo.window
+            && lcd.lcdc & 0x20 != 0
+            && self.window_triggered
+            && self.fifo.lx as u16 + 7 >= lcd.wx as u16
+        {
+            self.fifo.window = true;
+            self.fifo.background.clear();
+            self.fifo.restart_fetch();
+            self.fifo.discard = 7u8.saturating_sub(lcd.wx);
+        }
+
+        // A sprite starting at this pixel waits for the current background
+        // fetch to finish, then stalls the output while it is fetched
+        if self.fifo.discard == 0 && lcd.lcdc & 0x02 != 0 {
+            if let Some(sprite) = self.line_sprites.get(self.fifo.next_sprite).copied() {
+                if sprite.x <= self.fifo.lx + 8 {
+                    if self.fifo.step != FetchStep::Push {
+                        self.step_fetcher(bus);
+                        return false;
+                    }
+                    if self.fifo.background.is_empty() {
+                        self.fifo.push_tile();
+                    }
+                    self.fetch_sprite(bus, sprite);
+                    self.fifo.next_sprite += 1;
+                    self.fifo.stall = SPRITE_FETCH_DOTS - 1;
+                    return false;
+                }
+            }
+        }
+
+        if let Some(pixel) = self.fifo.background.pop_front() {
+            if self.fifo.discard > 0 {
+                self.fifo.discard -= 1;
+            } else {
+                self.draw_pixel(bus, pixel);
+            }
+        }
+        self.step_fetcher(bus);
+
+        if self.fifo.lx as usize == SCREEN_WIDTH {
+            if self.fifo.window {
+                self.window_line += 1;
+            }
+            return true;
+        }
+        false
+    }
+
+    // Mixes a background pixel with the sprite FIFO and sends it to the LCD,
+    // palettes are read as the pixel is drawn
+    fn draw_pixel(&mut self, bus: &Bus, background: BgPixel) {
+        let sprite = self.fifo.sprites.pop_front();
+        let offset = bus.lcd().ly as usize * SCREEN_WIDTH;
+        self.draw(
+            offset + self.fifo.lx as usize,
+            mix_pixel(bus, background, sprite),
+        );
+        self.fifo.lx += 1;
+    }
+
+    fn step_fetcher(&mut self, bus: &Bus) {
+        if self.fifo.step != FetchStep::Push {
+            self.fifo.step_dot += 1;
+            if self.fifo.step_dot < 2 {
+                return;
+            }
+            self.fifo.step_dot = 0;
+        }
+
+        let lcd = bus.lcd();
+        let vram = bus.vram();
+        let (map, x, y) = if self.fifo.window {
+            let map = if lcd.lcdc & 0x40 != 0 { 0x1C00 } else { 0x1800 };
+            (map, self.fifo.fetch_x, self.window_line)
+        } else {
+            let map = if lcd.lcdc & 0x08 != 0 { 0x1C00 } else { 0x1800 };
+            let x = (lcd.scx / 8).wrapping_add(self.fifo.fetch_x);
+            (map, x, lcd.scy.wrapping_add(lcd.ly))
+        };
+        let attributes = BgPixel::blank(self.fifo.attributes);
+        let row = if attributes.y_flip() {
+            7 - y % 8
+        } else {
+            y % 8
+        };
+        let address = (self.tile_index(bus, self.fifo.tile) * 16) as u16 + row as u16 * 2;
+        let bank = attributes.bank();
+        let cgb = bus.cgb();
+
+        let fifo = &mut self.fifo;
+        match fifo.step {
+            FetchStep::Tile => {
+                let map_address = map + (y as u16 / 8) * 32 + (x as u16 % 32);
+                fifo.tile = vram.read_bank(0, map_address);
+                fifo.attributes = if cgb {
+                    vram.read_bank(1, map_address)
+                } else {
+                    0
+                };
+                fifo.step = FetchStep::DataLow;
+            }
+            FetchStep::DataLow => {
+                fifo.lo = vram.read_bank(bank, address);
+                fifo.step = FetchStep::DataHigh;
+            }
+            FetchStep::DataHigh => {
+                fifo.hi = vram.read_bank(bank, address + 1);
+                fifo.step = FetchStep::Push;
+            }
+            // Tiles are only pushed once the FIFO has run empty
+            FetchStep::Push => {
+                if fifo.background.is_empty() {
+                    fifo.push_tile();
+                }
+            }
+        }
+    }
+
+    // Reads a sprite's row and merges it into the sprite FIFO, pixels already
+    // in the FIFO belong to sprites with a smaller X and are kept unless
+    // CGB mode gives priority to the sprite earlier in OAM
+    fn fetch_sprite(&mut self, bus: &Bus, sprite: Sprite) {
+        let mut row = bus.lcd().ly.wrapping_sub(sprite.y.wrapping_sub(16));
+        if sprite.y_flip() {
+            row = sprite.height - 1 - row;
+        }
+        let tile = match sprite.height {
+            16 => (sprite.tile & 0xFE) as u16 + row as u16 / 8,
+            _ => sprite.tile as u16,
+        };
+        let address = tile * 16 + (row as u16 % 8) * 2;
+        let cgb = bus.cgb();
+        let bank = if cgb { sprite.bank() } else { 0 };
+        let lo = bus.vram().read_bank(bank, address);
+        let hi = bus.vram().read_bank(bank, address + 1);
+
+        // Sprites partially off the left edge skip their hidden columns
+        let skip = 8u8.saturating_sub(sprite.x);
+        for column in skip..8 {
+            let bit = if sprite.x_flip() { column } else { 7 - column };
+            let pixel = SpritePixel {
+                color: ColorId::from_bits(lo >> bit, hi >> bit),
+                sprite,
+            };
+            let index = (column - skip) as usize;
+            match self.fifo.sprites.get_mut(index) {
+                Some(existing)
+                    if existing.color == ColorId::Zero
+                        || (cgb
+                            && pixel.color != ColorId::Zero
+                            && sprite.index < existing.sprite.index) =>
+                {
+                    *existing = pixel
+                }
+                Some(_) => (),
+                None => self.fifo.sprites.push_back(pixel),
+            }
+        }
+    }
+}
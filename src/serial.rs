@@ -0,0 +1,136 @@
+mod capture;
+mod socket;
+
+pub use capture::CaptureLink;
+pub use socket::SocketLink;
+
+pub const SB: u16 = 0xFF01;
+pub const SC: u16 = 0xFF02;
+
+// Bits take 512 T-cycles with the internal 8192 Hz clock
+const TRANSFER_CYCLES: u32 = 8 * 512;
+// How often a link is checked for transfers clocked by the other side
+const POLL_CYCLES: u32 = 512;
+
+// The other end of the link cable
+pub trait SerialLink: Send {
+    // Shifts out a byte as a transfer clocked by this Game Boy starts
+    fn send(&mut self, data: u8);
+
+    // Byte shifted in from the other side in return for the last byte sent,
+    // 0xFF if nothing is connected. Asked for once the transfer's clock ran
+    // out, None keeps the transfer going until the byte arrives
+    fn receive(&mut self) -> Option<u8>;
+
+    // Checks for a transfer clocked by the other side, replying with the
+    // byte in SB if a transfer is waiting for the external clock. Returns the
+    // byte received
+    fn poll(&mut self, _reply: Option<u8>) -> Option<u8> {
+        None
+    }
+}
+
+// An unplugged link cable, reads 0xFF and never clocks a transfer
+pub struct NullLink;
+
+impl SerialLink for NullLink {
+    fn send(&mut self, _data: u8) {}
+
+    fn receive(&mut self) -> Option<u8> {
+        Some(0xFF)
+    }
+}
+
+pub struct Serial {
+    sb: u8,
+    // Transfer start flag and clock select, the other bits read as set
+    sc: u8,
+    link: Box<dyn SerialLink>,
+    // T-cycles until an internally clocked transfer completes
+    transfer_cycles: u32,
+    poll_cycles: u32,
+}
+
+impl Default for Serial {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Serial {
+    pub fn new() -> Serial {
+        Serial {
+            sb: 0,
+            sc: 0,
+            link: Box::new(NullLink),
+            transfer_cycles: 0,
+            poll_cycles: 0,
+        }
+    }
+
+    pub fn connect(&mut self, link: Box<dyn SerialLink>) {
+        self.link = link;
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            SB => self.sb,
+            SC => self.sc | 0x7E,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, data: u8) {
+        match address {
+            SB => self.sb = data,
+            SC => {
+                self.sc = data & 0x81;
+                if self.internal_transfer() {
+                    self.transfer_cycles = TRANSFER_CYCLES;
+                    self.link.send(self.sb);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // Advances a transfer by a number of T-cycles, returns true if the
+    // serial interrupt should be requested
+    pub fn tick(&mut self, cycles: u32) -> bool {
+        if self.internal_transfer() {
+            if cycles < self.transfer_cycles {
+                self.transfer_cycles -= cycles;
+                return false;
+            }
+            // A reply still on its way is checked for again after a while
+            let Some(data) = self.link.receive() else {
+                self.transfer_cycles = POLL_CYCLES;
+                return false;
+            };
+            self.sb = data;
+            self.sc &= 0x7F;
+            return true;
+        }
+
+        self.poll_cycles += cycles;
+        if self.poll_cycles < POLL_CYCLES {
+            return false;
+        }
+        self.poll_cycles = 0;
+        // Only a transfer waiting for the external clock takes part, the
+        // other side reads 0xFF otherwise
+        let waiting = self.sc & 0x80 != 0;
+        match self.link.poll(waiting.then_some(self.sb)) {
+            Some(data) if waiting => {
+                self.sb = data;
+                self.sc &= 0x7F;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn internal_transfer(&self) -> bool {
+        self.sc & 0x81 == 0x81
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::SerialLink;
+
+// Collects every byte sent over the link, test ROMs print their results
+// this way. Clones share the same buffer so the host can keep one to read
+#[derive(Clone, Default)]
+pub struct CaptureLink {
+    output: Arc<Mutex<Vec<u8>>>,
+}
+
+impl CaptureLink {
+    pub fn new() -> CaptureLink {
+        CaptureLink::default()
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        self.output().clone()
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.output()).into_owned()
+    }
+
+    pub fn clear(&self) {
+        self.output().clear();
+    }
+
+    // A clone that panicked while holding the lock can't leave the buffer in
+    // a broken state, so a poisoned lock is still used
+    fn output(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.output
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl SerialLink for CaptureLink {
+    fn send(&mut self, data: u8) {
+        self.output().push(data);
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        Some(0xFF)
+    }
+}
@@ -0,0 +1,151 @@
+use std::io::{ErrorKind, Read, Result, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::SerialLink;
+
+// How long the clocking side waits for the other emulator to answer before
+// the transfer reads 0xFF
+const EXCHANGE_TIMEOUT: Duration = Duration::from_secs(1);
+
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_nonblocking(nonblocking),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_nonblocking(nonblocking),
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        let mut buffer = [0];
+        match self {
+            Stream::Tcp(stream) => stream.read_exact(&mut buffer)?,
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read_exact(&mut buffer)?,
+        }
+        Ok(buffer[0])
+    }
+
+    fn write_byte(&mut self, data: u8) -> Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.write_all(&[data]),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write_all(&[data]),
+        }
+    }
+}
+
+// Link cable between two emulators on the same machine. The side driving
+// the clock sends its byte and waits for the other side's byte in return,
+// the socket is never waited on so emulation carries on meanwhile
+pub struct SocketLink {
+    stream: Stream,
+    // When the byte of the running transfer was sent
+    sent: Option<Instant>,
+    // Replies that timed out, they are dropped when they arrive late so they
+    // aren't taken for transfers started by the other side
+    stale: u32,
+}
+
+impl SocketLink {
+    // Waits for the other emulator to connect
+    pub fn listen_tcp(address: impl ToSocketAddrs) -> Result<SocketLink> {
+        let (stream, _) = TcpListener::bind(address)?.accept()?;
+        Self::tcp(stream)
+    }
+
+    pub fn connect_tcp(address: impl ToSocketAddrs) -> Result<SocketLink> {
+        Self::tcp(TcpStream::connect(address)?)
+    }
+
+    fn tcp(stream: TcpStream) -> Result<SocketLink> {
+        // Every transfer is a single byte, don't hold it back
+        stream.set_nodelay(true)?;
+        Ok(Self::new(Stream::Tcp(stream)))
+    }
+
+    fn new(stream: Stream) -> SocketLink {
+        SocketLink {
+            stream,
+            sent: None,
+            stale: 0,
+        }
+    }
+
+    #[cfg(unix)]
+    pub fn listen_unix(path: impl AsRef<Path>) -> Result<SocketLink> {
+        let (stream, _) = UnixListener::bind(path)?.accept()?;
+        Ok(Self::new(Stream::Unix(stream)))
+    }
+
+    #[cfg(unix)]
+    pub fn connect_unix(path: impl AsRef<Path>) -> Result<SocketLink> {
+        Ok(Self::new(Stream::Unix(UnixStream::connect(path)?)))
+    }
+
+    fn try_send(&mut self, data: u8) -> Result<()> {
+        self.stream.set_nonblocking(false)?;
+        self.stream.write_byte(data)
+    }
+
+    // Reads the next byte if one arrived, skipping stale replies
+    fn try_read(&mut self) -> Result<Option<u8>> {
+        self.stream.set_nonblocking(true)?;
+        loop {
+            match self.stream.read_byte() {
+                Ok(_) if self.stale > 0 => self.stale -= 1,
+                Ok(data) => return Ok(Some(data)),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn try_poll(&mut self, reply: Option<u8>) -> Result<Option<u8>> {
+        let Some(data) = self.try_read()? else {
+            return Ok(None);
+        };
+        self.stream.set_nonblocking(false)?;
+        self.stream.write_byte(reply.unwrap_or(0xFF))?;
+        Ok(Some(data))
+    }
+}
+
+impl SerialLink for SocketLink {
+    // A lost connection behaves like an unplugged cable
+    fn send(&mut self, data: u8) {
+        self.sent = self.try_send(data).is_ok().then(Instant::now);
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        let Some(sent) = self.sent else {
+            return Some(0xFF);
+        };
+        let data = match self.try_read() {
+            Ok(Some(data)) => data,
+            Ok(None) if sent.elapsed() < EXCHANGE_TIMEOUT => return None,
+            Ok(None) => {
+                self.stale += 1;
+                0xFF
+            }
+            Err(_) => 0xFF,
+        };
+        self.sent = None;
+        Some(data)
+    }
+
+    fn poll(&mut self, reply: Option<u8>) -> Option<u8> {
+        self.try_poll(reply).unwrap_or(None)
+    }
+}
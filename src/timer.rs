@@ -0,0 +1,192 @@
+pub const DIV: u16 = 0xFF04;
+pub const TIMA: u16 = 0xFF05;
+pub const TMA: u16 = 0xFF06;
+pub const TAC: u16 = 0xFF07;
+
+pub struct Timer {
+    // Internal 16-bit counter, DIV is its upper byte
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    // TIMA overflowed during the last M-cycle and reads as 0 until reloaded
+    overflow: bool,
+    // TIMA was reloaded from TMA during the last M-cycle
+    reloading: bool,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timer {
+    pub fn new() -> Timer {
+        Timer {
+            counter: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            overflow: false,
+            reloading: false,
+        }
+    }
+
+    // Advances the timer by a number of T-cycles, returns true if the timer
+    // interrupt should be requested
+    pub fn tick(&mut self, cycles: u32) -> bool {
+        let mut interrupt = false;
+        for _ in 0..cycles / 4 {
+            interrupt |= self.step();
+        }
+        interrupt
+    }
+
+    // Advances the timer by one M-cycle
+    fn step(&mut self) -> bool {
+        self.reloading = false;
+        let mut interrupt = false;
+        if self.overflow {
+            // Reload happens one M-cycle after the overflow
+            self.overflow = false;
+            self.reloading = true;
+            self.tima = self.tma;
+            interrupt = true;
+        }
+
+        let old = self.signal();
+        self.counter = self.counter.wrapping_add(4);
+        if old && !self.signal() {
+            self.increment_tima();
+        }
+        interrupt
+    }
+
+    // Internal counter, DIV bit 4 drives the APU frame sequencer
+    pub fn counter(&self) -> u16 {
+        self.counter
+    }
+
+    pub fn set_counter(&mut self, counter: u16) {
+        self.counter = counter;
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            DIV => (self.counter >> 8) as u8,
+            TIMA => self.tima,
+            TMA => self.tma,
+            TAC => self.tac | 0xF8,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, data: u8) {
+        match address {
+            DIV => {
+                // Resetting the counter can cause a falling edge on the selected bit
+                let old = self.signal();
+                self.counter = 0;
+                if old {
+                    self.increment_tima();
+                }
+            }
+            // Writes during the reload cycle are ignored, writes in the cycle
+            // before it cancel the reload
+            TIMA if !self.reloading => {
+                self.tima = data;
+                self.overflow = false;
+            }
+            TMA => {
+                self.tma = data;
+                if self.reloading {
+                    self.tima = data;
+                }
+            }
+            TAC => {
+                // Disabling the timer or switching frequency can also cause a
+                // falling edge
+                let old = self.signal();
+                self.tac = data & 0x07;
+                if old && !self.signal() {
+                    self.increment_tima();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // Value of the counter bit selected by TAC, ANDed with the enable bit
+    fn signal(&self) -> bool {
+        let bit = match self.tac & 0x03 {
+            0x00 => 9,
+            0x01 => 3,
+            0x02 => 5,
+            _ => 7,
+        };
+        self.tac & 0x04 != 0 && (self.counter >> bit) & 1 != 0
+    }
+
+    fn increment_tima(&mut self) {
+        let (value, overflow) = self.tima.overflowing_add(1);
+        self.tima = value;
+        self.overflow = overflow;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Timer counting on bit 3 of the counter, every 16 T-cycles
+    fn timer(tima: u8) -> Timer {
+        let mut timer = Timer::new();
+        timer.write(TAC, 0x05);
+        timer.write(TMA, 0x42);
+        timer.write(TIMA, tima);
+        timer
+    }
+
+    #[test]
+    fn reload_is_delayed_one_m_cycle() {
+        let mut timer = timer(0xFF);
+        assert!(!timer.tick(16));
+        assert_eq!(timer.read(TIMA), 0x00);
+        assert!(timer.tick(4));
+        assert_eq!(timer.read(TIMA), 0x42);
+    }
+
+    #[test]
+    fn tima_write_cancels_pending_reload() {
+        let mut timer = timer(0xFF);
+        timer.tick(16);
+        timer.write(TIMA, 0x10);
+        assert!(!timer.tick(4));
+        assert_eq!(timer.read(TIMA), 0x10);
+    }
+
+    #[test]
+    fn div_write_falling_edge_increments_tima() {
+        let mut timer = timer(0x00);
+        timer.set_counter(0x0008);
+        timer.write(DIV, 0);
+        assert_eq!(timer.read(TIMA), 0x01);
+        // No edge with the selected bit clear
+        timer.write(DIV, 0);
+        assert_eq!(timer.read(TIMA), 0x01);
+    }
+
+    #[test]
+    fn tac_write_falling_edge_increments_tima() {
+        let mut timer = timer(0x00);
+        timer.set_counter(0x0008);
+        // Switching to bit 5, which is clear
+        timer.write(TAC, 0x06);
+        assert_eq!(timer.read(TIMA), 0x01);
+        timer.write(TAC, 0x05);
+        // Disabling the timer
+        timer.write(TAC, 0x01);
+        assert_eq!(timer.read(TIMA), 0x02);
+    }
+}